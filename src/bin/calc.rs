@@ -0,0 +1,9 @@
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // Log to stdout (if you run with `RUST_LOG=debug`).
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    calculator_rs::cli::run(&args)
+}
@@ -1,60 +1,376 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+mod constants;
+mod currency;
+mod definitions;
+mod error;
+mod functions;
+mod integers;
+mod number;
+mod parser;
+mod programmer;
+mod units;
+
+pub use constants::{Constant, CONSTANTS};
+pub use currency::{is_currency_code, RateTable};
+pub use definitions::{UserFunction, MAX_CALL_DEPTH};
+pub use error::CalcError;
+pub use functions::{AngleMode, Function};
+pub use number::{ComplexDisplay, DivisionMode, FractionDisplay, Number, Precision};
+pub use parser::{is_variable_name, parse, ParseError};
+pub use programmer::{Base, ProgrammerMode, WordSize};
+pub use units::{Quantity, Unit, Units};
+
+/// Number of memory registers, labelled M1, M2, ...
+pub const MEMORY_SLOTS: usize = 4;
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Events {
     Add,
     Sub,
     Mul,
     Div,
+    Mod,
+    IntDiv,
+    Pow,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Rol,
+    Ror,
     Neg,
+    /// Replaces the shown number by its bitwise complement.
+    Not,
+    /// Marks the shown number as a percentage, see `Tokens::Percent`.
+    Percent,
+    OpenParen,
+    CloseParen,
+    /// Separates the arguments of a function such as `gcd`.
+    Comma,
+    /// A digit, 10 to 15 standing for A to F in hexadecimal.
     Number(i64),
+    Decimal,
+    /// Enters a ready-made operand such as a constant.
+    Value(Number),
+    /// Opens a call, or applies the function to the number being typed.
+    Function(Function),
+    /// Replaces the shown number by its factorial.
+    Factorial,
+    /// Switches to the next fraction form, turning fraction mode on.
+    CycleFraction,
     Eq,
     Backspace,
     Reset,
+    /// Puts the result of the given history entry into the accumulator.
+    RecallResult(usize),
+    /// Restores the given history entry as the pending expression.
+    RecallExpression(usize),
+    ClearHistory,
+    /// Memory register events, each naming the slot it works on.
+    MemoryClear(usize),
+    MemoryRecall(usize),
+    MemoryAdd(usize),
+    MemorySub(usize),
+    MemoryStore(usize),
+    /// Binds the shown number to a name that passes `is_variable_name`.
+    StoreVariable(String),
+    DeleteVariable(String),
+    DeleteFunction(String),
     #[allow(dead_code)]
     Idle,
 }
 
 #[derive(Debug, PartialEq, Clone)]
-enum Tokens {
+pub enum Tokens {
     Add,
     Sub,
     Mul,
     Div,
-    Number(i64),
+    /// Remainder, `%` or `mod`.
+    Mod,
+    /// Whole quotient, `//` or `div`.
+    IntDiv,
+    Pow,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    /// Rotations within the programmer mode word, 64 bits otherwise.
+    Rol,
+    Ror,
+    /// Unary minus, only produced by the text parser.
+    Neg,
+    /// Bitwise complement, a prefix like `Neg`.
+    Not,
+    /// The last result, typed as `ans`.
+    Ans,
+    /// A named value from the calculator's variables.
+    Variable(String),
+    /// `name = expression`, binding weaker than any other operator.
+    Assign,
+    /// Call of a built-in function, always followed by its paren group.
+    Function(Function),
+    /// Call of a user function with its argument count, which
+    /// `shunting_yard` fills in from the commas of the paren group.
+    Call(String, usize),
+    /// Separates the arguments of a call.
+    Comma,
+    /// One of a unit, such as `km`.
+    Unit(Unit),
+    /// One of a currency by its code, such as `EUR`, priced from the rate
+    /// table when evaluated.
+    Currency(String),
+    /// Attaches a unit to the operand before it: `5 km` is `5 UnitMul km`.
+    /// Binds tighter than `Mul` so that `5 km / 2 h` is a speed.
+    UnitMul,
+    /// Converts to the units on its right, `to` or `in`.
+    Convert,
+    /// Postfix `!`, extended to non-integers by the gamma function.
+    Factorial,
+    /// Postfix `%`: a hundredth of its operand, except as the right side of
+    /// `+` or `-`, where it is that share of the left side, so `200 + 10%`
+    /// is 220 while `200 * 10%` is 20.
+    Percent,
+    OpenParen,
+    CloseParen,
+    Number(Number),
+}
+
+impl Tokens {
+    /// Binding strength of an operator; parens and operands bind nothing.
+    /// Bitwise operators rank below arithmetic as in C.
+    fn precedence(&self) -> u8 {
+        match self {
+            Tokens::Assign => 1,
+            Tokens::Convert => 2,
+            Tokens::Or => 3,
+            Tokens::Xor => 4,
+            Tokens::And => 5,
+            Tokens::Shl | Tokens::Shr | Tokens::Rol | Tokens::Ror => 6,
+            Tokens::Add | Tokens::Sub => 7,
+            Tokens::Mul | Tokens::Div | Tokens::Mod | Tokens::IntDiv => 8,
+            Tokens::UnitMul => 9,
+            Tokens::Neg | Tokens::Not => 10,
+            Tokens::Pow => 11,
+            Tokens::Function(_) | Tokens::Call(..) => 12,
+            Tokens::Factorial | Tokens::Percent => 13,
+            Tokens::OpenParen
+            | Tokens::CloseParen
+            | Tokens::Comma
+            | Tokens::Number(_)
+            | Tokens::Ans
+            | Tokens::Variable(_)
+            | Tokens::Unit(_)
+            | Tokens::Currency(_) => 0,
+        }
+    }
+
+    /// Whether a chain like `2^3^2` groups from the right.
+    fn right_associative(&self) -> bool {
+        matches!(self, Tokens::Pow | Tokens::Assign)
+    }
+}
+
+impl fmt::Display for Tokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tokens::Add => write!(f, "+"),
+            Tokens::Sub | Tokens::Neg => write!(f, "-"),
+            Tokens::Mul => write!(f, "×"),
+            Tokens::Div => write!(f, "÷"),
+            Tokens::Mod => write!(f, "mod"),
+            Tokens::IntDiv => write!(f, "div"),
+            Tokens::Pow => write!(f, "^"),
+            Tokens::And => write!(f, "and"),
+            Tokens::Or => write!(f, "or"),
+            Tokens::Xor => write!(f, "xor"),
+            Tokens::Shl => write!(f, "shl"),
+            Tokens::Shr => write!(f, "shr"),
+            Tokens::Rol => write!(f, "rol"),
+            Tokens::Ror => write!(f, "ror"),
+            Tokens::Not => write!(f, "not"),
+            Tokens::Ans => write!(f, "ans"),
+            Tokens::Variable(name) => write!(f, "{}", name),
+            Tokens::Assign => write!(f, "="),
+            Tokens::Call(name, _) => write!(f, "{}", name),
+            Tokens::Comma => write!(f, ","),
+            Tokens::Unit(unit) => write!(f, "{}", unit.name),
+            Tokens::Currency(code) => write!(f, "{}", code),
+            Tokens::UnitMul => Ok(()),
+            Tokens::Convert => write!(f, "to"),
+            Tokens::Factorial => write!(f, "!"),
+            Tokens::Percent => write!(f, "%"),
+            Tokens::Function(func) => write!(f, "{}", func.name()),
+            Tokens::OpenParen => write!(f, "("),
+            Tokens::CloseParen => write!(f, ")"),
+            Tokens::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Renders infix tokens for people, e.g. "(2 + 3) × 4".
+fn render(tokens: &[Tokens]) -> String {
+    let mut text = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if *token == Tokens::UnitMul {
+            continue;
+        }
+        let tight = i == 0
+            || matches!(
+                token,
+                Tokens::CloseParen | Tokens::Comma | Tokens::Factorial | Tokens::Percent
+            )
+            || matches!(
+                tokens[i - 1],
+                Tokens::OpenParen
+                    | Tokens::Neg
+                    | Tokens::Not
+                    | Tokens::Function(_)
+                    | Tokens::Call(..)
+            );
+        if !tight {
+            text.push(' ');
+        }
+        text += &token.to_string();
+    }
+    text
+}
+
+/// A completed calculation.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub expression: String,
+    pub result: Number,
+    tokens: Vec<Tokens>,
 }
 
+#[derive(Default)]
 pub struct Calculator {
     ops: Vec<Tokens>,
-    accumulator: i64,
+    accumulator: Number,
+    /// Digits of the number being typed, empty when showing a result.
+    input: String,
+    error: Option<CalcError>,
+    /// Result of the last successful calculation.
+    ans: Quantity,
+    history: Vec<HistoryEntry>,
+    memory: [Number; MEMORY_SLOTS],
+    division: DivisionMode,
+    angle: AngleMode,
+    /// Integer-only arithmetic in a chosen base and word size.
+    programmer: Option<ProgrammerMode>,
+    precision: Precision,
+    /// Exact quotients shown as fractions instead of rounded decimals.
+    fractions: Option<FractionDisplay>,
+    complex: ComplexDisplay,
+    /// Values assigned with `name = expression`, by name.
+    variables: BTreeMap<String, Quantity>,
+    /// Functions defined with `f(x, y) = expression`, by name.
+    functions: BTreeMap<String, UserFunction>,
+    /// Exchange rates that currency codes are priced from.
+    rates: Option<RateTable>,
+    /// Whether the last result came from `factor` and is shown as a
+    /// product of primes, until the next key.
+    factored: bool,
 }
 
-impl Default for Calculator {
-    fn default() -> Self {
-        Self {
-            ops: vec![],
-            accumulator: 0,
+/// Moves the call that owns a just closed paren group to the output,
+/// with the number of arguments the group held. Built-in functions must
+/// get exactly as many as they take.
+fn close_call(
+    operator_stack: &mut Vec<Tokens>,
+    output_queue: &mut Vec<Tokens>,
+    args: usize,
+) -> Result<(), CalcError> {
+    match operator_stack.last() {
+        Some(Tokens::Function(func)) if func.arity() != args => {
+            return Err(CalcError::ArgumentCount {
+                name: func.name().to_string(),
+                expected: func.arity(),
+                found: args,
+            })
         }
+        Some(Tokens::Function(_)) => output_queue.push(operator_stack.pop().unwrap()),
+        Some(Tokens::Call(..)) => {
+            if let Some(Tokens::Call(name, _)) = operator_stack.pop() {
+                output_queue.push(Tokens::Call(name, args));
+            }
+        }
+        _ => {}
     }
+    Ok(())
 }
 
-fn shunting_yard(tokens: Vec<Tokens>) -> Vec<Tokens> {
+fn shunting_yard(tokens: Vec<Tokens>) -> Result<Vec<Tokens>, CalcError> {
     let mut output_queue = vec![];
     let mut operator_stack = vec![];
+    // argument count of every open paren group
+    let mut group_args = vec![];
 
     for token in tokens {
         match token {
-            Tokens::Number(n) => output_queue.push(Tokens::Number(n)),
-            Tokens::Add | Tokens::Sub => {
-                while let Some(top) = operator_stack.last() {
-                    if *top == Tokens::Add || *top == Tokens::Sub {
-                        output_queue.push(operator_stack.pop().unwrap());
-                    } else {
-                        break;
+            Tokens::Number(_)
+            | Tokens::Ans
+            | Tokens::Variable(_)
+            | Tokens::Unit(_)
+            | Tokens::Currency(_) => output_queue.push(token),
+            // postfix operators bind tightest and apply to the operand
+            // just output
+            Tokens::Factorial | Tokens::Percent => output_queue.push(token),
+            // prefix operators wait for their operand
+            Tokens::OpenParen => {
+                group_args.push(1);
+                operator_stack.push(token)
+            }
+            Tokens::Neg | Tokens::Not | Tokens::Function(_) | Tokens::Call(..) => {
+                operator_stack.push(token)
+            }
+            Tokens::Comma => {
+                while !matches!(operator_stack.last(), Some(Tokens::OpenParen) | None) {
+                    output_queue.push(operator_stack.pop().unwrap());
+                }
+                match group_args.last_mut() {
+                    Some(args) => *args += 1,
+                    None => return Err(CalcError::MalformedExpression),
+                }
+            }
+            Tokens::CloseParen => {
+                loop {
+                    match operator_stack.pop() {
+                        Some(Tokens::OpenParen) => break,
+                        Some(top) => output_queue.push(top),
+                        None => return Err(CalcError::MalformedExpression),
                     }
                 }
-                operator_stack.push(token);
+                // the group was the argument list of a call
+                let args = group_args.pop().unwrap_or(1);
+                close_call(&mut operator_stack, &mut output_queue, args)?;
             }
-            Tokens::Mul | Tokens::Div => {
+            Tokens::Add
+            | Tokens::Sub
+            | Tokens::Mul
+            | Tokens::Div
+            | Tokens::Mod
+            | Tokens::IntDiv
+            | Tokens::Pow
+            | Tokens::And
+            | Tokens::Or
+            | Tokens::Xor
+            | Tokens::Shl
+            | Tokens::Shr
+            | Tokens::Rol
+            | Tokens::Ror
+            | Tokens::Assign
+            | Tokens::UnitMul
+            | Tokens::Convert => {
                 while let Some(top) = operator_stack.last() {
-                    if *top == Tokens::Mul || *top == Tokens::Div {
+                    if top.precedence() > token.precedence()
+                        || (top.precedence() == token.precedence() && !token.right_associative())
+                    {
                         output_queue.push(operator_stack.pop().unwrap());
                     } else {
                         break;
@@ -65,90 +381,1048 @@ fn shunting_yard(tokens: Vec<Tokens>) -> Vec<Tokens> {
         }
     }
 
+    // unbalanced open parens are closed implicitly
     while let Some(op) = operator_stack.pop() {
-        output_queue.push(op);
+        if op == Tokens::OpenParen {
+            let args = group_args.pop().unwrap_or(1);
+            close_call(&mut operator_stack, &mut output_queue, args)?;
+        } else {
+            output_queue.push(op);
+        }
     }
 
-    output_queue
+    Ok(output_queue)
+}
+
+/// Takes the two topmost operands, right-hand side on top.
+fn pop_operands<T>(stack: &mut Vec<T>) -> Result<(T, T), CalcError> {
+    let y = stack.pop().ok_or(CalcError::MalformedExpression)?;
+    let x = stack.pop().ok_or(CalcError::MalformedExpression)?;
+    Ok((x, y))
 }
 
 impl Calculator {
-    fn calculate(&mut self) -> i64 {
-        println!("Ops: {:?}", self.ops.clone());
-        println!("Algo: {:?}", shunting_yard(self.ops.clone()));
+    /// False right after a closing paren: the group is the operand, so the
+    /// accumulator must not be pushed again.
+    fn operand_pending(&self) -> bool {
+        !matches!(self.ops.last(), Some(Tokens::CloseParen | Tokens::Percent))
+    }
+
+    fn unclosed_parens(&self) -> usize {
+        let opened = self.ops.iter().filter(|t| **t == Tokens::OpenParen).count();
+        let closed = self
+            .ops
+            .iter()
+            .filter(|t| **t == Tokens::CloseParen)
+            .count();
+        opened - closed
+    }
+
+    /// Pushes the current entry as an operand and starts a fresh one.
+    fn push_accumulator(&mut self) {
+        self.ops
+            .push(Tokens::Number(std::mem::take(&mut self.accumulator)));
+        self.input.clear();
+    }
+
+    /// Makes `value` the current operand, like a result that was just
+    /// calculated; right after a group it multiplies the group.
+    fn enter_value(&mut self, value: Number) {
+        if !self.operand_pending() {
+            self.ops.push(Tokens::Mul);
+        }
+        self.accumulator = value;
+        self.input.clear();
+    }
+
+    /// Evaluates the keypad expression. The keypad shows plain numbers, so
+    /// a result with units (from `ans` or a variable) gives its magnitude.
+    fn calculate(&mut self) -> Result<Number, CalcError> {
+        tracing::debug!("Ops: {:?}", self.ops);
+        let result = self.evaluate_tokens(self.ops.clone(), &BTreeMap::new(), 0)?;
+        let magnitude = result.magnitude()?;
+        self.ans = result;
+        Ok(magnitude)
+    }
+
+    /// Evaluates a typed expression such as "3*(4+2)/7", remembering the
+    /// result as `ans`. A function definition gives no result.
+    pub fn evaluate(&mut self, input: &str) -> Result<Option<Quantity>, CalcError> {
+        let tokens = parse(input)?;
+        if let Some(function) = UserFunction::from_tokens(&tokens) {
+            self.functions.insert(function.name.clone(), function);
+            return Ok(None);
+        }
+        let result = self.evaluate_tokens(tokens, &BTreeMap::new(), 0)?;
+        self.ans = result.clone();
+        Ok(Some(result))
+    }
+
+    /// Runs the tokens through the shunting yard and evaluates the RPN.
+    /// `locals` are the parameters of the user function being called
+    /// `depth` levels deep; they hide variables of the same name. An
+    /// assignment stores its value only when the whole right-hand side
+    /// evaluated.
+    fn evaluate_tokens(
+        &mut self,
+        tokens: Vec<Tokens>,
+        locals: &BTreeMap<String, Quantity>,
+        depth: usize,
+    ) -> Result<Quantity, CalcError> {
+        let rpn = shunting_yard(tokens)?;
+        tracing::debug!("Algo: {:?}", rpn);
+        if depth == 0 {
+            self.factored = rpn.last() == Some(&Tokens::Function(Function::Factor));
+        }
+        let assigns = rpn.last() == Some(&Tokens::Assign);
+        let mut target = None;
         let mut stack = vec![];
 
-        for token in shunting_yard(self.ops.clone()) {
+        // whether the operand on top of the stack is a percentage
+        let mut percent = false;
+
+        for (index, token) in rpn.into_iter().enumerate() {
+            let is_percent = token == Tokens::Percent;
             match token {
-                Tokens::Number(n) => stack.push(n),
-                Tokens::Add => {
-                    let y = stack.pop().unwrap();
-                    let x = stack.pop().unwrap();
-                    stack.push(x + y);
+                Tokens::Number(n) => stack.push(Quantity::from(self.normalize(n))),
+                Tokens::Unit(unit) => stack.push(Quantity::of(unit)),
+                Tokens::Currency(code) => {
+                    let unit = self.rates.as_ref().and_then(|rates| rates.unit(&code));
+                    stack.push(Quantity::of(unit.ok_or(CalcError::MissingRate(code))?));
+                }
+                Tokens::Ans => {
+                    let mut ans = self.ans.clone();
+                    ans.number = self.normalize(ans.number);
+                    stack.push(ans);
+                }
+                // the name assigned to comes first and is not read
+                Tokens::Variable(name) if assigns && index == 0 => target = Some(name),
+                Tokens::Variable(name) => {
+                    let mut value = locals
+                        .get(&name)
+                        .or_else(|| self.variables.get(&name))
+                        .cloned()
+                        .ok_or(CalcError::UndefinedVariable(name))?;
+                    value.number = self.normalize(value.number);
+                    stack.push(value);
+                }
+                Tokens::Call(name, args) => match self.functions.get(&name).cloned() {
+                    Some(function) => {
+                        if args != function.params.len() {
+                            return Err(CalcError::ArgumentCount {
+                                name,
+                                expected: function.params.len(),
+                                found: args,
+                            });
+                        }
+                        if depth >= MAX_CALL_DEPTH {
+                            return Err(CalcError::RecursionLimit);
+                        }
+                        if stack.len() < args {
+                            return Err(CalcError::MalformedExpression);
+                        }
+                        let values = stack.split_off(stack.len() - args);
+                        let scope = function.params.iter().cloned().zip(values).collect();
+                        let result =
+                            self.evaluate_tokens(function.body().to_vec(), &scope, depth + 1)?;
+                        stack.push(result);
+                    }
+                    // without a user function of its name, `c(2)` multiplies
+                    // the constant as it would without the call
+                    None => {
+                        let constant = Constant::from_name(&name)
+                            .filter(|_| args == 1)
+                            .ok_or(CalcError::UndefinedFunction(name))?;
+                        let x = stack.pop().ok_or(CalcError::MalformedExpression)?;
+                        let value = Quantity::from(self.normalize(constant.value()));
+                        stack.push(self.combine(&Tokens::Mul, value, x)?);
+                    }
+                },
+                Tokens::Assign => {
+                    let name = target.take().ok_or(CalcError::MalformedExpression)?;
+                    let value = stack.last().ok_or(CalcError::MalformedExpression)?;
+                    self.variables.insert(name, value.clone());
+                }
+                Tokens::Neg => {
+                    let x = stack.pop().ok_or(CalcError::MalformedExpression)?;
+                    let number = self.binary(&Tokens::Sub, &Number::default(), &x.number)?;
+                    stack.push(Quantity { number, ..x });
+                }
+                Tokens::Not => {
+                    let x = stack.pop().ok_or(CalcError::MalformedExpression)?;
+                    x.units.check_compatible(&Units::default())?;
+                    stack.push(Quantity::from(Number::from(
+                        self.word().not(x.number.to_int()?),
+                    )));
+                }
+                Tokens::Function(func) => {
+                    if stack.len() < func.arity() {
+                        return Err(CalcError::MalformedExpression);
+                    }
+                    let args = stack.split_off(stack.len() - func.arity());
+                    // only functions that keep the size of a value take units
+                    if !matches!(
+                        func,
+                        Function::Abs | Function::Conj | Function::Re | Function::Im
+                    ) {
+                        for arg in &args {
+                            arg.units.check_compatible(&Units::default())?;
+                        }
+                    }
+                    let units = args[0].units.clone();
+                    let numbers: Vec<_> = args.into_iter().map(|q| q.number).collect();
+                    let number = self.normalize(func.call(&numbers, self.angle)?);
+                    stack.push(Quantity { number, units });
+                }
+                Tokens::Factorial => {
+                    let x = stack.pop().ok_or(CalcError::MalformedExpression)?;
+                    x.units.check_compatible(&Units::default())?;
+                    stack.push(Quantity::from(self.normalize(x.number.factorial()?)));
+                }
+                Tokens::Percent => {
+                    let x = stack.pop().ok_or(CalcError::MalformedExpression)?;
+                    x.units.check_compatible(&Units::default())?;
+                    let number = x.number.div(&Number::from(100))?;
+                    stack.push(Quantity::from(self.normalize(number)));
                 }
-                Tokens::Sub => {
-                    let y = stack.pop().unwrap();
-                    let x = stack.pop().unwrap();
-                    stack.push(x - y);
+                // `x + y%` adds y percent of x
+                op @ (Tokens::Add | Tokens::Sub) if percent => {
+                    let (x, y) = pop_operands(&mut stack)?;
+                    let share = self.combine(&Tokens::Mul, x.clone(), y)?;
+                    stack.push(self.combine(&op, x, share)?);
                 }
-                Tokens::Mul => {
-                    let y = stack.pop().unwrap();
-                    let x = stack.pop().unwrap();
-                    stack.push(x * y);
+                Tokens::OpenParen | Tokens::CloseParen | Tokens::Comma => unreachable!(),
+                op => {
+                    let (x, y) = pop_operands(&mut stack)?;
+                    stack.push(self.combine(&op, x, y)?);
+                }
+            }
+            percent = is_percent;
+        }
+
+        match (stack.pop(), stack.is_empty()) {
+            (Some(result), true) => Ok(result),
+            _ => Err(CalcError::MalformedExpression),
+        }
+    }
+
+    /// Applies a binary operator token to quantities. Sums, remainders and
+    /// conversions need operands of the same dimension and keep the units
+    /// of the left one (or the right one for conversions); products and
+    /// quotients combine units; everything else works on plain numbers.
+    fn combine(&self, op: &Tokens, x: Quantity, y: Quantity) -> Result<Quantity, CalcError> {
+        let units = match op {
+            Tokens::Mul | Tokens::UnitMul => x.units.mul(&y.units)?,
+            Tokens::Div => x.units.div(&y.units)?,
+            Tokens::Pow => {
+                y.units.check_compatible(&Units::default())?;
+                if x.units.is_empty() {
+                    Units::default()
+                } else {
+                    let n = y.number.to_int()?;
+                    x.units
+                        .powi(i32::try_from(n).map_err(|_| CalcError::Overflow)?)?
                 }
-                Tokens::Div => {
-                    let y = stack.pop().unwrap();
-                    let x = stack.pop().unwrap();
-                    stack.push(x / y);
+            }
+            Tokens::Add | Tokens::Sub | Tokens::Mod | Tokens::IntDiv | Tokens::Convert => {
+                x.units.check_compatible(&y.units)?;
+                match op {
+                    Tokens::Convert => y.units.clone(),
+                    Tokens::IntDiv => Units::default(),
+                    _ if x.units.is_empty() => y.units.clone(),
+                    _ => x.units.clone(),
                 }
             }
+            _ => {
+                x.units.check_compatible(&Units::default())?;
+                y.units.check_compatible(&Units::default())?;
+                Units::default()
+            }
+        };
+        let number = match op {
+            Tokens::Convert => x.number,
+            Tokens::UnitMul => self.binary(&Tokens::Mul, &x.number, &y.number)?,
+            // SI values stay exact so that e.g. 5 km / 2 h shows 2.5 km/h
+            Tokens::Div if !(x.units.is_empty() && y.units.is_empty()) => {
+                x.number.div(&y.number)?
+            }
+            _ => self.binary(op, &x.number, &y.number)?,
+        };
+        Ok(Quantity { number, units })
+    }
+
+    /// Applies a binary operator token. Quotients that never terminate are
+    /// rounded to the precision unless fraction mode keeps them exact;
+    /// programmer mode and the bitwise operators
+    /// use wrapping integer arithmetic.
+    fn binary(&self, op: &Tokens, x: &Number, y: &Number) -> Result<Number, CalcError> {
+        if self.programmer.is_some() {
+            let result = self
+                .word()
+                .apply(op, x.to_int()?, y.to_int()?, self.division)?;
+            return Ok(Number::from(result));
+        }
+        match op {
+            Tokens::Add => x.add(y),
+            Tokens::Sub => x.sub(y),
+            Tokens::Mul => x.mul(y),
+            Tokens::Div => Ok(self.limit(x.div(y)?)),
+            Tokens::Mod => x.modulo(y, self.division),
+            Tokens::IntDiv => x.int_div(y, self.division),
+            Tokens::Pow => Ok(self.limit(x.pow(y)?)),
+            _ => Ok(Number::from(self.word().apply(
+                op,
+                x.to_int()?,
+                y.to_int()?,
+                self.division,
+            )?)),
+        }
+    }
+
+    /// Rounds a quotient to the precision outside fraction mode.
+    fn limit(&self, n: Number) -> Number {
+        match self.fractions {
+            Some(_) => n,
+            None => n.limit(self.precision),
+        }
+    }
+
+    /// Word size for bitwise operators.
+    fn word(&self) -> WordSize {
+        self.programmer.map(|p| p.word).unwrap_or_default()
+    }
+
+    /// In programmer mode every value is truncated and wrapped to the word.
+    fn normalize(&self, n: Number) -> Number {
+        match self.programmer {
+            Some(p) => Number::from(p.word.wrap(n.low_bits())),
+            None => n,
+        }
+    }
+
+    /// Reads the digits being typed, in the programmer base if one is set.
+    fn parse_input(&self) -> Number {
+        match self.programmer {
+            Some(p) => {
+                let digits = self.input.trim_start_matches('-');
+                let n = p.parse(digits).unwrap_or_default();
+                let n = if digits.len() < self.input.len() {
+                    -n
+                } else {
+                    n
+                };
+                Number::from(p.word.wrap(n))
+            }
+            None => Number::parse(&self.input).unwrap_or_default(),
+        }
+    }
+
+    pub fn programmer(&self) -> Option<ProgrammerMode> {
+        self.programmer
+    }
+
+    /// Switches programmer mode on or off; the shown value is truncated to
+    /// fit the new word.
+    pub fn set_programmer(&mut self, mode: Option<ProgrammerMode>) {
+        self.programmer = mode;
+        self.accumulator = self.normalize(self.accumulator.clone());
+        self.input.clear();
+    }
+
+    /// Whether the digit key `digit` can be typed in the current base.
+    pub fn accepts_digit(&self, digit: i64) -> bool {
+        let radix = self.programmer.map_or(10, |p| p.base.radix());
+        (0..radix as i64).contains(&digit)
+    }
+
+    /// Renders a value in the programmer base, as a fraction in fraction
+    /// mode, complex numbers in the chosen form, or plainly otherwise.
+    pub fn format(&self, n: &Number) -> String {
+        if let (Number::Complex(z), ComplexDisplay::Polar) = (n, self.complex) {
+            let arg = Function::Arg
+                .call(std::slice::from_ref(n), self.angle)
+                .unwrap_or_default();
+            return format!("{}∠{}", Number::Float(z.norm()), arg);
+        }
+        match (self.programmer, n.to_int(), self.fractions) {
+            (Some(p), Ok(i), _) => p.format(i),
+            (None, _, Some(form)) => n.to_fraction(form),
+            _ => n.to_string(),
+        }
+    }
+
+    pub fn complex_display(&self) -> ComplexDisplay {
+        self.complex
+    }
+
+    pub fn set_complex_display(&mut self, form: ComplexDisplay) {
+        self.complex = form;
+    }
+
+    /// Renders a quantity like `format` followed by its units, e.g.
+    /// "5.3 km".
+    pub fn format_quantity(&self, q: &Quantity) -> String {
+        match q.magnitude() {
+            Ok(n) if q.units.is_empty() => self.format(&n),
+            Ok(n) => format!("{} {}", self.format(&n), q.units),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    /// Formats a result of `evaluate` like `format_quantity`, or as a
+    /// product of primes when the expression was a call of `factor`.
+    pub fn format_result(&self, q: &Quantity) -> String {
+        if self.factored {
+            if let Ok(factors) = q
+                .number
+                .to_bigint()
+                .and_then(|n| integers::factorization(&n))
+            {
+                return factors;
+            }
+        }
+        self.format_quantity(q)
+    }
+
+    /// Assigned variables, sorted by name.
+    pub fn variables(&self) -> &BTreeMap<String, Quantity> {
+        &self.variables
+    }
+
+    /// User functions, sorted by name.
+    pub fn functions(&self) -> impl Iterator<Item = &UserFunction> {
+        self.functions.values()
+    }
+
+    pub fn rates(&self) -> Option<&RateTable> {
+        self.rates.as_ref()
+    }
+
+    pub fn set_rates(&mut self, rates: Option<RateTable>) {
+        self.rates = rates;
+    }
+
+    pub fn fractions(&self) -> Option<FractionDisplay> {
+        self.fractions
+    }
+
+    /// Turns fraction mode on with the given form, or off with `None`.
+    pub fn set_fractions(&mut self, form: Option<FractionDisplay>) {
+        let turned_on = self.fractions.is_none() && form.is_some();
+        self.fractions = form;
+        if turned_on {
+            self.recalculate_exact();
+        }
+    }
+
+    /// Calculates the shown result again once fraction mode is on, as its
+    /// quotients were rounded to the precision before. Keypad expressions
+    /// hold plain numbers only, so nothing else has changed since.
+    fn recalculate_exact(&mut self) {
+        if !self.ops.is_empty() || !self.input.is_empty() || self.error.is_some() {
+            return;
+        }
+        let tokens = match self.history.last() {
+            Some(entry) if entry.result == self.accumulator => entry.tokens.clone(),
+            _ => return,
+        };
+        let result = match self.evaluate_tokens(tokens, &BTreeMap::new(), 0) {
+            Ok(result) => result,
+            Err(_) => return,
+        };
+        if let Ok(magnitude) = result.magnitude() {
+            self.accumulator = magnitude.clone();
+            if let Some(entry) = self.history.last_mut() {
+                entry.result = magnitude;
+            }
+            self.ans = result;
+        }
+    }
+
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.precision = precision;
+    }
+
+    pub fn set_division_mode(&mut self, mode: DivisionMode) {
+        self.division = mode;
+    }
+
+    pub fn angle_mode(&self) -> AngleMode {
+        self.angle
+    }
+
+    pub fn set_angle_mode(&mut self, mode: AngleMode) {
+        self.angle = mode;
+    }
+
+    /// The operators and operands queued so far, e.g. "12 + 7 ×".
+    pub fn expression(&self) -> String {
+        render(&self.ops)
+    }
+
+    /// Completed calculations, oldest first.
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    /// Content of a memory register.
+    pub fn memory(&self, slot: usize) -> &Number {
+        &self.memory[slot]
+    }
+
+    /// True when any memory register holds a non-zero value.
+    pub fn has_memory(&self) -> bool {
+        self.memory.iter().any(|m| !m.is_zero())
+    }
+
+    /// Adds `value` to a memory register, entering the error state on
+    /// overflow. The shown number counts as finished afterwards.
+    fn memory_add(&mut self, slot: usize, value: Number) {
+        match self.binary(&Tokens::Add, &self.memory[slot], &value) {
+            Ok(sum) => self.memory[slot] = sum,
+            Err(err) => self.error = Some(err),
         }
-        stack.pop().unwrap()
+        self.input.clear();
+    }
+
+    /// The error of the last calculation, cleared by `Reset` or new digits.
+    pub fn error(&self) -> Option<&CalcError> {
+        self.error.as_ref()
     }
 
     pub fn display(&self) -> String {
-        self.accumulator.to_string()
+        if self.error.is_some() {
+            "Error".to_string()
+        } else if self.input.is_empty() {
+            self.format_result(&Quantity::from(self.accumulator.clone()))
+        } else {
+            self.input.clone()
+        }
     }
 
     pub fn dispatch(&mut self, event: Events) {
+        self.factored = false;
+        if self.error.is_some() {
+            match event {
+                Events::Reset
+                | Events::Number(_)
+                | Events::Decimal
+                | Events::RecallResult(_)
+                | Events::RecallExpression(_)
+                | Events::MemoryRecall(_)
+                | Events::Value(_) => self.error = None,
+                _ => return,
+            }
+        }
+
         match event {
             Events::Idle => {}
             Events::Eq => {
-                // here will be complex logic
-                self.ops.push(Tokens::Number(self.accumulator));
-                self.accumulator = self.calculate();
+                if self.operand_pending() {
+                    self.push_accumulator();
+                }
+                for _ in 0..self.unclosed_parens() {
+                    self.ops.push(Tokens::CloseParen);
+                }
+                match self.calculate() {
+                    Ok(result) => {
+                        self.history.push(HistoryEntry {
+                            expression: render(&self.ops),
+                            result: result.clone(),
+                            tokens: self.ops.clone(),
+                        });
+                        self.accumulator = result;
+                    }
+                    Err(err) => {
+                        self.accumulator = Number::default();
+                        self.error = Some(err);
+                    }
+                }
                 self.ops.clear();
             }
             Events::Reset => {
                 self.ops.clear();
-                self.accumulator = 0;
+                self.accumulator = Number::default();
+                self.input.clear();
+            }
+            Events::RecallResult(index) => {
+                if let Some(entry) = self.history.get(index) {
+                    self.enter_value(entry.result.clone());
+                }
+            }
+            Events::RecallExpression(index) => {
+                if let Some(entry) = self.history.get(index) {
+                    self.ops = entry.tokens.clone();
+                    // the trailing operand becomes editable again
+                    self.accumulator = match self.ops.last().cloned() {
+                        Some(Tokens::Number(n)) => {
+                            self.ops.pop();
+                            n
+                        }
+                        _ => Number::default(),
+                    };
+                    self.input.clear();
+                }
+            }
+            Events::ClearHistory => self.history.clear(),
+            Events::MemoryClear(slot) => self.memory[slot] = Number::default(),
+            Events::MemoryRecall(slot) => self.enter_value(self.memory[slot].clone()),
+            Events::Value(value) => self.enter_value(value),
+            // "5 nCr 2" reads as nCr(5, 2)
+            Events::Function(func) if !self.input.is_empty() && func.arity() > 1 => {
+                self.ops.push(Tokens::Function(func));
+                self.ops.push(Tokens::OpenParen);
+                self.push_accumulator();
+                self.ops.push(Tokens::Comma);
+            }
+            Events::Function(func) => {
+                if self.input.is_empty() {
+                    if !self.operand_pending() {
+                        self.ops.push(Tokens::Mul);
+                    }
+                    self.ops.push(Tokens::Function(func));
+                    self.ops.push(Tokens::OpenParen);
+                } else {
+                    // "30 sin" reads as sin(30)
+                    self.ops.push(Tokens::Function(func));
+                    self.ops.push(Tokens::OpenParen);
+                    self.push_accumulator();
+                    self.ops.push(Tokens::CloseParen);
+                }
+            }
+            Events::Factorial => match self.accumulator.factorial() {
+                Ok(result) => self.enter_value(self.normalize(result)),
+                Err(err) => self.error = Some(err),
+            },
+            Events::CycleFraction => {
+                self.set_fractions(Some(
+                    self.fractions.map_or_else(Default::default, |f| f.next()),
+                ));
+            }
+            Events::MemoryAdd(slot) => self.memory_add(slot, self.accumulator.clone()),
+            Events::MemorySub(slot) => self.memory_add(slot, self.accumulator.neg()),
+            Events::Not => {
+                let x = self.accumulator.to_int().map(|x| self.word().not(x));
+                match x {
+                    Ok(result) => self.enter_value(Number::from(result)),
+                    Err(err) => self.error = Some(err),
+                }
+            }
+            Events::MemoryStore(slot) => {
+                self.memory[slot] = self.accumulator.clone();
+                self.input.clear();
+            }
+            Events::StoreVariable(name) => {
+                self.variables
+                    .insert(name, Quantity::from(self.accumulator.clone()));
+                self.input.clear();
+            }
+            Events::DeleteVariable(name) => {
+                self.variables.remove(&name);
+            }
+            Events::DeleteFunction(name) => {
+                self.functions.remove(&name);
             }
             Events::Neg => {
-                self.accumulator *= -1;
+                if self.input.is_empty() {
+                    self.accumulator = self.normalize(self.accumulator.neg());
+                } else {
+                    if self.input.starts_with('-') {
+                        self.input.remove(0);
+                    } else {
+                        self.input.insert(0, '-');
+                    }
+                    self.accumulator = self.parse_input();
+                }
             }
             Events::Number(num) => {
-                if self.accumulator <= 999_999_999_9 {
-                    self.accumulator *= 10;
-                    self.accumulator += num as i64;
+                if !self.accepts_digit(num) {
+                    return;
+                }
+                // digits right after a group multiply it: "(2+3)4"
+                if !self.operand_pending() {
+                    self.ops.push(Tokens::Mul);
+                }
+                if let Some(p) = self.programmer {
+                    // only digits that still fit the word are taken
+                    let mut typed = self.input.trim_start_matches(['-', '0']).to_string();
+                    typed.push(
+                        char::from_digit(num as u32, 16)
+                            .unwrap()
+                            .to_ascii_uppercase(),
+                    );
+                    if p.parse(&typed).is_some() {
+                        self.input = if self.input.starts_with('-') {
+                            format!("-{}", typed)
+                        } else {
+                            typed
+                        };
+                        self.accumulator = self.parse_input();
+                    }
+                } else {
+                    // a lone leading zero is replaced rather than extended
+                    if self.input.trim_start_matches('-') == "0" {
+                        self.input.pop();
+                    }
+                    self.input.push_str(&num.to_string());
+                    self.accumulator = self.parse_input();
+                }
+            }
+            // programmer mode has no fractions
+            Events::Decimal if self.programmer.is_some() => {}
+            Events::Decimal => {
+                if !self.operand_pending() {
+                    self.ops.push(Tokens::Mul);
+                }
+                if self.input.is_empty() {
+                    self.input.push('0');
+                }
+                if !self.input.contains('.') {
+                    self.input.push('.');
                 }
             }
             Events::Backspace => {
-                self.accumulator = self.accumulator / 10;
+                // a shown exact result can be edited like typed digits,
+                // fractions in their decimal form
+                if self.input.is_empty() {
+                    if let Number::Exact(_) = self.accumulator {
+                        self.input = match self.programmer {
+                            Some(_) => self.format(&self.accumulator),
+                            None => self.accumulator.to_string(),
+                        };
+                    }
+                }
+                self.input.pop();
+                if self.input.is_empty() || self.input == "-" {
+                    self.input.clear();
+                    self.accumulator = Number::default();
+                } else {
+                    self.accumulator = self.parse_input();
+                }
+            }
+            Events::OpenParen => {
+                if !self.operand_pending() {
+                    self.ops.push(Tokens::Mul);
+                }
+                self.ops.push(Tokens::OpenParen);
+                self.accumulator = Number::default();
+                self.input.clear();
+            }
+            // applied when the expression is calculated, as it depends on
+            // the operator before the number
+            Events::Percent => {
+                if self.operand_pending() {
+                    self.push_accumulator();
+                }
+                self.ops.push(Tokens::Percent);
+            }
+            Events::Comma => {
+                if self.operand_pending() {
+                    self.push_accumulator();
+                }
+                self.ops.push(Tokens::Comma);
             }
-            op @ (Events::Add | Events::Sub | Events::Mul | Events::Div) => {
+            Events::CloseParen => {
+                if self.unclosed_parens() > 0 {
+                    if self.operand_pending() {
+                        self.push_accumulator();
+                    }
+                    self.ops.push(Tokens::CloseParen);
+                }
+            }
+            op @ (Events::Add
+            | Events::Sub
+            | Events::Mul
+            | Events::Div
+            | Events::Mod
+            | Events::IntDiv
+            | Events::Pow
+            | Events::And
+            | Events::Or
+            | Events::Xor
+            | Events::Shl
+            | Events::Shr
+            | Events::Rol
+            | Events::Ror) => {
                 // operation first
                 let op_token: Option<Tokens> = match op {
                     Events::Add => Some(Tokens::Add),
                     Events::Sub => Some(Tokens::Sub),
                     Events::Mul => Some(Tokens::Mul),
                     Events::Div => Some(Tokens::Div),
+                    Events::Mod => Some(Tokens::Mod),
+                    Events::IntDiv => Some(Tokens::IntDiv),
+                    Events::Pow => Some(Tokens::Pow),
+                    Events::And => Some(Tokens::And),
+                    Events::Or => Some(Tokens::Or),
+                    Events::Xor => Some(Tokens::Xor),
+                    Events::Shl => Some(Tokens::Shl),
+                    Events::Shr => Some(Tokens::Shr),
+                    Events::Rol => Some(Tokens::Rol),
+                    Events::Ror => Some(Tokens::Ror),
                     _ => None,
                 };
 
-                self.ops.push(Tokens::Number(self.accumulator));
+                if self.operand_pending() {
+                    self.push_accumulator();
+                }
                 self.ops.push(op_token.unwrap());
-                self.accumulator = 0
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(calculator: &mut Calculator, events: impl IntoIterator<Item = Events>) {
+        for event in events {
+            calculator.dispatch(event);
+        }
+    }
+
+    /// Types the digits of `n` on the keypad.
+    fn type_number(calculator: &mut Calculator, n: i64) {
+        for digit in n.to_string().chars() {
+            calculator.dispatch(Events::Number(digit.to_digit(10).unwrap().into()));
+        }
+    }
+
+    /// Result of keying `x op y %` followed by `=`.
+    fn percent_of(x: i64, op: Events, y: i64) -> String {
+        let mut calculator = Calculator::default();
+        type_number(&mut calculator, x);
+        calculator.dispatch(op);
+        type_number(&mut calculator, y);
+        press(&mut calculator, [Events::Percent, Events::Eq]);
+        calculator.display()
+    }
+
+    #[test]
+    fn fractions_add_exactly() {
+        let mut calculator = Calculator::default();
+        calculator.set_fractions(Some(FractionDisplay::Improper));
+        let result = calculator.evaluate("1/3 + 1/6").unwrap().unwrap();
+        assert_eq!(calculator.format_result(&result), "1/2");
+    }
+
+    #[test]
+    fn fraction_mode_recalculates_the_shown_result() {
+        let mut calculator = Calculator::default();
+        press(
+            &mut calculator,
+            [
+                Events::Number(1),
+                Events::Div,
+                Events::Number(3),
+                Events::Eq,
+            ],
+        );
+        calculator.dispatch(Events::CycleFraction);
+        assert_eq!(calculator.display(), "1/3");
+        assert_eq!(
+            calculator.history()[0].result,
+            Number::from(1).div(&Number::from(3)).unwrap()
+        );
+    }
+
+    /// Evaluates `input` and formats the result as the REPL prints it.
+    fn show(calculator: &mut Calculator, input: &str) -> String {
+        let result = calculator.evaluate(input).unwrap().unwrap();
+        calculator.format_result(&result)
+    }
+
+    #[test]
+    fn quantities() {
+        let mut calculator = Calculator::default();
+        assert_eq!(show(&mut calculator, "5 km + 300 m"), "5.3 km");
+        assert_eq!(show(&mut calculator, "60 mph to km/h"), "96.56064 km/h");
+        assert_eq!(show(&mut calculator, "3 ft * 2 ft"), "6 ft²");
+        assert_eq!(show(&mut calculator, "5 km / 2 h"), "2.5 km/h");
+        assert_eq!(
+            calculator.evaluate("5 km + 3 s").err(),
+            Some(CalcError::IncompatibleUnits(
+                "km".to_string(),
+                "s".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn units_after_a_fraction() {
+        let mut calculator = Calculator::default();
+        assert_eq!(show(&mut calculator, "1/2 mi to ft"), "2640 ft");
+        assert_eq!(
+            show(&mut calculator, "1/3 km to m"),
+            "333.33333333333333333 m"
+        );
+        assert_eq!(show(&mut calculator, "1/4 km to m"), "250 m");
+        assert_eq!(show(&mut calculator, "-1/4 km"), "-0.25 km");
+        // with spaces the unit stays with the divisor
+        assert_eq!(show(&mut calculator, "10 / 2 h"), "5 1/h");
+        assert_eq!(show(&mut calculator, "2^1/2 m"), "1 1/m");
+    }
+
+    #[test]
+    fn unit_powers_overflow() {
+        let mut calculator = Calculator::default();
+        assert!(calculator.evaluate("2 m^127").is_ok());
+        for input in [
+            "2 m^128",
+            "(2 m^100) * (3 m^100)",
+            "(2 m)^(2^31)",
+            "(2 m^2)^(2^30)",
+        ] {
+            assert_eq!(
+                calculator.evaluate(input).err(),
+                Some(CalcError::Overflow),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn user_function_calls() {
+        let mut calculator = Calculator::default();
+        assert_eq!(calculator.evaluate("f(x, y) = x^2 + y"), Ok(None));
+        let result = calculator.evaluate("f(3, 4)").unwrap().unwrap();
+        assert_eq!(result.number, Number::from(13));
+        assert_eq!(
+            calculator.evaluate("f(3)").err(),
+            Some(CalcError::ArgumentCount {
+                name: "f".to_string(),
+                expected: 2,
+                found: 1,
+            })
+        );
+        assert_eq!(
+            calculator.evaluate("u(1)").err(),
+            Some(CalcError::UndefinedFunction("u".to_string()))
+        );
+    }
+
+    #[test]
+    fn parameters_hide_variables() {
+        let mut calculator = Calculator::default();
+        calculator.evaluate("x = 10").unwrap();
+        calculator.evaluate("y = 1").unwrap();
+        calculator.evaluate("f(x) = x + y").unwrap();
+        let result = calculator.evaluate("f(2)").unwrap().unwrap();
+        assert_eq!(result.number, Number::from(3));
+        let result = calculator.evaluate("x").unwrap().unwrap();
+        assert_eq!(result.number, Number::from(10));
+    }
+
+    #[test]
+    fn recursion_stops_at_the_call_depth_limit() {
+        let mut calculator = Calculator::default();
+        calculator.evaluate("f(x) = f(x)").unwrap();
+        assert_eq!(
+            calculator.evaluate("f(1)").err(),
+            Some(CalcError::RecursionLimit)
+        );
+        // a chain just within the limit
+        calculator.evaluate("g0(x) = x").unwrap();
+        for depth in 1..MAX_CALL_DEPTH {
+            let definition = format!("g{}(x) = g{}(x) + 1", depth, depth - 1);
+            calculator.evaluate(&definition).unwrap();
+        }
+        let call = format!("g{}(0)", MAX_CALL_DEPTH - 1);
+        let result = calculator.evaluate(&call).unwrap().unwrap();
+        assert_eq!(result.number, Number::from(MAX_CALL_DEPTH as i128 - 1));
+        let definition = format!("g{}(x) = g{}(x) + 1", MAX_CALL_DEPTH, MAX_CALL_DEPTH - 1);
+        calculator.evaluate(&definition).unwrap();
+        let call = format!("g{}(0)", MAX_CALL_DEPTH);
+        assert_eq!(
+            calculator.evaluate(&call).err(),
+            Some(CalcError::RecursionLimit)
+        );
+    }
+
+    #[test]
+    fn duplicate_parameters_are_rejected() {
+        let mut calculator = Calculator::default();
+        match calculator.evaluate("f(x, x) = x") {
+            Err(CalcError::Parse(err)) => assert_eq!(err.position, 8),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(calculator.functions().count(), 0);
+    }
+
+    #[test]
+    fn delete_function() {
+        let mut calculator = Calculator::default();
+        calculator.evaluate("f(x) = 2*x").unwrap();
+        calculator.evaluate("u(x) = 3*x").unwrap();
+        calculator.dispatch(Events::DeleteFunction("f".to_string()));
+        let names: Vec<_> = calculator.functions().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["u"]);
+        assert_eq!(
+            calculator.evaluate("f(1)").err(),
+            Some(CalcError::UndefinedFunction("f".to_string()))
+        );
+    }
+
+    #[test]
+    fn user_functions_take_constant_names() {
+        let mut calculator = Calculator::default();
+        let planck = Constant::from_name("h").unwrap().value();
+        let result = calculator.evaluate("h(2)").unwrap().unwrap();
+        assert_eq!(result.number, planck.mul(&Number::from(2)).unwrap());
+
+        assert_eq!(calculator.evaluate("h(x) = x*3"), Ok(None));
+        let result = calculator.evaluate("h(2)").unwrap().unwrap();
+        assert_eq!(result.number, Number::from(6));
+        let result = calculator.evaluate("h").unwrap().unwrap();
+        assert_eq!(result.number, planck);
+    }
+
+    #[test]
+    fn constants_cannot_be_assigned() {
+        let mut calculator = Calculator::default();
+        match calculator.evaluate("c = 3") {
+            Err(CalcError::Parse(err)) => {
+                assert_eq!(err.to_string(), "`c` is a built-in constant at position 1")
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn percent_key() {
+        assert_eq!(percent_of(200, Events::Add, 10), "220");
+        assert_eq!(percent_of(200, Events::Sub, 10), "180");
+        assert_eq!(percent_of(200, Events::Mul, 10), "20");
+        assert_eq!(percent_of(200, Events::Div, 10), "2000");
+
+        let mut calculator = Calculator::default();
+        type_number(&mut calculator, 50);
+        press(&mut calculator, [Events::Percent, Events::Eq]);
+        assert_eq!(calculator.display(), "0.5");
+    }
+
+    #[test]
+    fn powers_associate_right_and_bind_before_negation() {
+        let mut calculator = Calculator::default();
+        for (input, expected) in [("2^3^2", 512), ("-2^2", -4), ("(-2)^2", 4)] {
+            let result = calculator.evaluate(input).unwrap().unwrap();
+            assert_eq!(result.number, Number::from(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn powers_of_one_keep_their_sign() {
+        let mut calculator = Calculator::default();
+        for (input, expected) in [
+            ("(-1)^(10^400+1)", -1),
+            ("(-1)^(10^400)", 1),
+            ("1^(10^400+1)", 1),
+            ("0^(10^400)", 0),
+        ] {
+            let result = calculator.evaluate(input).unwrap().unwrap();
+            assert_eq!(result.number, Number::from(expected), "{}", input);
+        }
+    }
+}
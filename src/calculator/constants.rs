@@ -0,0 +1,68 @@
+use std::f64::consts;
+
+use super::Number;
+
+/// A named value usable in any expression.
+#[derive(Debug, Clone, Copy)]
+pub struct Constant {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// SI unit of the value, empty for pure numbers.
+    pub unit: &'static str,
+    value: f64,
+}
+
+impl Constant {
+    const fn new(
+        name: &'static str,
+        description: &'static str,
+        unit: &'static str,
+        value: f64,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            unit,
+            value,
+        }
+    }
+
+    pub fn value(&self) -> Number {
+        Number::Float(self.value)
+    }
+
+    /// Looks a constant up by name; `π`, `τ` and `φ` stand for `pi`, `tau`
+    /// and `phi`.
+    pub fn from_name(name: &str) -> Option<&'static Constant> {
+        let name = match name {
+            "π" => "pi",
+            "τ" => "tau",
+            "φ" => "phi",
+            _ => name,
+        };
+        CONSTANTS.iter().find(|c| c.name == name)
+    }
+}
+
+/// Mathematical constants followed by physical ones, with CODATA 2018
+/// values.
+pub const CONSTANTS: [Constant; 18] = [
+    Constant::new("pi", "ratio of circumference to diameter", "", consts::PI),
+    Constant::new("e", "Euler's number", "", consts::E),
+    Constant::new("tau", "full turn, 2π", "", consts::TAU),
+    Constant::new("phi", "golden ratio", "", 1.618_033_988_749_895),
+    Constant::new("c", "speed of light in vacuum", "m/s", 299_792_458.0),
+    Constant::new("h", "Planck constant", "J s", 6.626_070_15e-34),
+    Constant::new("hbar", "reduced Planck constant", "J s", 1.054_571_817e-34),
+    Constant::new("N_A", "Avogadro constant", "1/mol", 6.022_140_76e23),
+    Constant::new("k_B", "Boltzmann constant", "J/K", 1.380_649e-23),
+    Constant::new("q_e", "elementary charge", "C", 1.602_176_634e-19),
+    Constant::new("g", "standard gravity", "m/s²", 9.806_65),
+    Constant::new("G", "gravitational constant", "m³/(kg s²)", 6.674_30e-11),
+    Constant::new("m_e", "electron mass", "kg", 9.109_383_701_5e-31),
+    Constant::new("m_p", "proton mass", "kg", 1.672_621_923_69e-27),
+    Constant::new("eps0", "vacuum permittivity", "F/m", 8.854_187_812_8e-12),
+    Constant::new("mu0", "vacuum permeability", "N/A²", 1.256_637_062_12e-6),
+    Constant::new("R", "molar gas constant", "J/(mol K)", 8.314_462_618),
+    Constant::new("atm", "standard atmosphere", "Pa", 101_325.0),
+];
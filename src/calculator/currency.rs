@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::{Number, Unit};
+
+/// Exchange rates read from a local file, never fetched. In TOML:
+///
+/// ```toml
+/// date = "2024-05-01"
+/// base = "USD"
+///
+/// [rates]
+/// EUR = 0.92
+/// GBP = 0.79
+/// ```
+///
+/// or the same keys as a JSON object. Each rate is the price of one unit of
+/// the base currency.
+#[derive(Debug, Clone)]
+pub struct RateTable {
+    /// Day the rates were taken, as written in the file.
+    pub date: String,
+    pub base: String,
+    rates: BTreeMap<String, Number>,
+}
+
+impl RateTable {
+    /// Reads a `.json` file as JSON and anything else as TOML.
+    pub fn load(path: &Path) -> Result<RateTable, String> {
+        let text =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let table = if path.extension().is_some_and(|ext| ext == "json") {
+            RateTable::from_json(&text)
+        } else {
+            RateTable::from_toml(&text)
+        };
+        table.map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn from_toml(text: &str) -> Result<RateTable, String> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|err: toml::de::Error| err.message().to_string())?;
+        let field = |key: &str| match table.get(key) {
+            Some(toml::Value::String(value)) => Ok(value.clone()),
+            _ => Err(format!("missing `{}`", key)),
+        };
+        let rates = match table.get("rates") {
+            Some(toml::Value::Table(rates)) => rates,
+            _ => return Err("missing `[rates]`".to_string()),
+        };
+        let rates = rates.iter().map(|(code, rate)| {
+            let rate = match rate {
+                toml::Value::Float(x) => Some(x.to_string()),
+                toml::Value::Integer(n) => Some(n.to_string()),
+                _ => None,
+            };
+            (code.clone(), rate)
+        });
+        RateTable::new(field("date")?, field("base")?, rates)
+    }
+
+    pub fn from_json(text: &str) -> Result<RateTable, String> {
+        let table: serde_json::Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let field = |key: &str| match table.get(key) {
+            Some(serde_json::Value::String(value)) => Ok(value.clone()),
+            _ => Err(format!("missing `{}`", key)),
+        };
+        let rates = match table.get("rates") {
+            Some(serde_json::Value::Object(rates)) => rates,
+            _ => return Err("missing `rates`".to_string()),
+        };
+        let rates = rates.iter().map(|(code, rate)| {
+            let rate = match rate {
+                serde_json::Value::Number(x) => Some(x.to_string()),
+                _ => None,
+            };
+            (code.clone(), rate)
+        });
+        RateTable::new(field("date")?, field("base")?, rates)
+    }
+
+    /// Builds the table from rates written as decimal text, so that `0.92`
+    /// is exactly 92/100 rather than the nearest float.
+    fn new(
+        date: String,
+        base: String,
+        entries: impl Iterator<Item = (String, Option<String>)>,
+    ) -> Result<RateTable, String> {
+        let mut rates = BTreeMap::new();
+        rates.insert(base.clone(), Number::from(1));
+        for (code, rate) in entries {
+            match rate.as_deref().and_then(Number::parse) {
+                Some(rate) if rate.as_f64() > 0.0 => {
+                    rates.insert(code, rate);
+                }
+                _ => return Err(format!("invalid rate for {}", code)),
+            }
+        }
+        Ok(RateTable { date, base, rates })
+    }
+
+    /// Currency codes with a rate, the base included.
+    pub fn currencies(&self) -> impl Iterator<Item = &str> {
+        self.rates.keys().map(String::as_str)
+    }
+
+    /// The currency as a unit measured in the base currency, `None` if the
+    /// table has no rate for it.
+    pub fn unit(&self, code: &str) -> Option<Unit> {
+        let value = Number::from(1).div(self.rates.get(code)?).ok()?;
+        Some(Unit::currency(code, value))
+    }
+}
+
+/// Whether `name` looks like an ISO 4217 currency code such as `EUR`.
+pub fn is_currency_code(name: &str) -> bool {
+    name.len() == 3 && name.chars().all(|c| c.is_ascii_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::{CalcError, Calculator};
+
+    const TOML: &str = r#"
+        date = "2024-05-01"
+        base = "USD"
+
+        [rates]
+        EUR = 0.92
+        JPY = 155
+    "#;
+
+    #[test]
+    fn reads_toml() {
+        let table = RateTable::from_toml(TOML).unwrap();
+        assert_eq!(table.date, "2024-05-01");
+        assert_eq!(table.base, "USD");
+        assert_eq!(
+            table.currencies().collect::<Vec<_>>(),
+            ["EUR", "JPY", "USD"]
+        );
+        assert_eq!(table.rates["EUR"], Number::parse("0.92").unwrap());
+    }
+
+    #[test]
+    fn reads_json() {
+        let json = r#"{"date": "2024-05-01", "base": "EUR", "rates": {"USD": 1.087}}"#;
+        let table = RateTable::from_json(json).unwrap();
+        assert_eq!(table.date, "2024-05-01");
+        assert_eq!(table.base, "EUR");
+        assert_eq!(table.currencies().collect::<Vec<_>>(), ["EUR", "USD"]);
+        assert_eq!(table.rates["USD"], Number::parse("1.087").unwrap());
+    }
+
+    #[test]
+    fn requires_date_base_and_rates() {
+        let without = |key: &str| {
+            let lines = TOML.lines().filter(|line| !line.trim().starts_with(key));
+            lines.collect::<Vec<_>>().join("\n")
+        };
+        assert_eq!(
+            RateTable::from_toml(&without("date")).unwrap_err(),
+            "missing `date`"
+        );
+        assert_eq!(
+            RateTable::from_toml(&without("base")).unwrap_err(),
+            "missing `base`"
+        );
+        assert_eq!(
+            RateTable::from_toml("date = \"2024-05-01\"\nbase = \"USD\"").unwrap_err(),
+            "missing `[rates]`"
+        );
+        assert_eq!(
+            RateTable::from_json(r#"{"date": "2024-05-01", "base": "USD"}"#).unwrap_err(),
+            "missing `rates`"
+        );
+        assert_eq!(
+            RateTable::from_json(r#"{"base": "USD", "rates": {}}"#).unwrap_err(),
+            "missing `date`"
+        );
+    }
+
+    #[test]
+    fn rejects_zero_and_non_numeric_rates() {
+        let table = |rate: &str| {
+            RateTable::from_toml(&format!(
+                "date = \"2024-05-01\"\nbase = \"USD\"\n[rates]\nEUR = {}",
+                rate
+            ))
+        };
+        assert_eq!(table("0").unwrap_err(), "invalid rate for EUR");
+        assert_eq!(table("-0.5").unwrap_err(), "invalid rate for EUR");
+        assert_eq!(table("\"0.92\"").unwrap_err(), "invalid rate for EUR");
+        let json = r#"{"date": "2024-05-01", "base": "USD", "rates": {"EUR": null}}"#;
+        assert_eq!(
+            RateTable::from_json(json).unwrap_err(),
+            "invalid rate for EUR"
+        );
+    }
+
+    #[test]
+    fn converts_between_currencies() {
+        let mut calculator = Calculator::default();
+        calculator.set_rates(Some(RateTable::from_toml(TOML).unwrap()));
+        let result = calculator.evaluate("120 USD to EUR").unwrap().unwrap();
+        assert_eq!(calculator.format_result(&result), "110.4 EUR");
+    }
+
+    #[test]
+    fn missing_rates() {
+        let mut calculator = Calculator::default();
+        assert_eq!(
+            calculator.evaluate("5 EUR").err(),
+            Some(CalcError::MissingRate("EUR".to_string()))
+        );
+        calculator.set_rates(Some(RateTable::from_toml(TOML).unwrap()));
+        assert_eq!(
+            calculator.evaluate("5 GBP").err(),
+            Some(CalcError::MissingRate("GBP".to_string()))
+        );
+    }
+}
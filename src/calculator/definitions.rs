@@ -0,0 +1,57 @@
+use std::fmt;
+
+use super::{render, Tokens};
+
+/// Deepest nesting of user function calls before evaluation gives up, so
+/// that `f(x) = f(x)` fails instead of overflowing the stack.
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// A function defined with `name(params) = body`.
+#[derive(Debug, Clone)]
+pub struct UserFunction {
+    pub name: String,
+    pub params: Vec<String>,
+    /// Infix tokens of the right-hand side, evaluated on every call.
+    body: Vec<Tokens>,
+}
+
+impl UserFunction {
+    /// Splits tokens of the form `f(x, y) = body`, as validated by the
+    /// parser, into a definition.
+    pub fn from_tokens(tokens: &[Tokens]) -> Option<UserFunction> {
+        let assign = tokens.iter().position(|t| *t == Tokens::Assign)?;
+        let (head, body) = (&tokens[..assign], &tokens[assign + 1..]);
+        let name = match head.first() {
+            Some(Tokens::Call(name, _)) => name.clone(),
+            _ => return None,
+        };
+        let params = head
+            .iter()
+            .filter_map(|t| match t {
+                Tokens::Variable(param) => Some(param.clone()),
+                _ => None,
+            })
+            .collect();
+        Some(UserFunction {
+            name,
+            params,
+            body: body.to_vec(),
+        })
+    }
+
+    pub fn body(&self) -> &[Tokens] {
+        &self.body
+    }
+}
+
+impl fmt::Display for UserFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}) = {}",
+            self.name,
+            self.params.join(", "),
+            render(&self.body)
+        )
+    }
+}
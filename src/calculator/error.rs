@@ -0,0 +1,67 @@
+use std::fmt;
+
+use super::ParseError;
+
+/// Reasons an expression cannot be evaluated.
+#[derive(Debug, PartialEq, Clone)]
+pub enum CalcError {
+    DivisionByZero,
+    /// The result does not fit into the number representation.
+    Overflow,
+    /// The operation is undefined for its operands, e.g. `ln(0)`.
+    Domain,
+    /// Operators and operands do not form a valid expression.
+    MalformedExpression,
+    /// A variable was read before anything was assigned to it.
+    UndefinedVariable(String),
+    UndefinedFunction(String),
+    /// A function was called with the wrong number of arguments.
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// Quantities of different dimensions were added or converted.
+    IncompatibleUnits(String, String),
+    /// User function calls nested deeper than `MAX_CALL_DEPTH`.
+    RecursionLimit,
+    /// A currency code with no rate in the loaded table, or no table.
+    MissingRate(String),
+    Parse(ParseError),
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::DivisionByZero => write!(f, "division by zero"),
+            CalcError::Overflow => write!(f, "overflow"),
+            CalcError::Domain => write!(f, "undefined result"),
+            CalcError::MalformedExpression => write!(f, "malformed expression"),
+            CalcError::UndefinedVariable(name) => write!(f, "unknown name '{}'", name),
+            CalcError::UndefinedFunction(name) => write!(f, "unknown function '{}'", name),
+            CalcError::ArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} takes {} argument(s) but was given {}",
+                name, expected, found
+            ),
+            CalcError::IncompatibleUnits(x, y) => {
+                write!(f, "incompatible units: {} and {}", x, y)
+            }
+            CalcError::RecursionLimit => write!(f, "too many nested calls"),
+            CalcError::MissingRate(code) => write!(f, "no exchange rate for {}", code),
+            CalcError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+impl From<ParseError> for CalcError {
+    fn from(err: ParseError) -> Self {
+        CalcError::Parse(err)
+    }
+}
@@ -0,0 +1,297 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use num::bigint::BigInt;
+use num::complex::Complex64;
+use num::integer::Integer;
+
+use super::{integers, CalcError, Number};
+
+/// Unit trigonometric functions read and produce angles in.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum AngleMode {
+    Degrees,
+    #[default]
+    Radians,
+    /// 400 gradians make a full turn.
+    Gradians,
+}
+
+impl AngleMode {
+    /// Size of a full turn in this unit.
+    fn full_turn(self) -> f64 {
+        match self {
+            AngleMode::Degrees => 360.0,
+            AngleMode::Radians => 2.0 * PI,
+            AngleMode::Gradians => 400.0,
+        }
+    }
+
+    fn to_radians(self, angle: f64) -> f64 {
+        angle / self.full_turn() * 2.0 * PI
+    }
+
+    fn radians_to_unit(self, radians: f64) -> f64 {
+        radians / (2.0 * PI) * self.full_turn()
+    }
+}
+
+impl fmt::Display for AngleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AngleMode::Degrees => write!(f, "DEG"),
+            AngleMode::Radians => write!(f, "RAD"),
+            AngleMode::Gradians => write!(f, "GRAD"),
+        }
+    }
+}
+
+/// Built-in functions, called as `name(x)` or `name(x, y)`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Ln,
+    Log10,
+    Log2,
+    Exp,
+    Sqrt,
+    Cbrt,
+    /// Magnitude, the modulus of complex numbers.
+    Abs,
+    /// Angle of a complex number in the current angle unit.
+    Arg,
+    Conj,
+    /// Real part.
+    Re,
+    /// Imaginary part.
+    Im,
+    /// Binomial coefficient `nCr(n, r)`.
+    NCr,
+    /// Arrangements `nPr(n, r)`.
+    NPr,
+    Gcd,
+    Lcm,
+    /// 1 for primes, 0 otherwise.
+    IsPrime,
+    /// The number itself, shown as a product of primes.
+    Factor,
+    Fib,
+    /// `mod_pow(b, e, m)` is `b^e mod m`.
+    ModPow,
+}
+
+impl Function {
+    pub const ALL: [Function; 28] = [
+        Function::Sin,
+        Function::Cos,
+        Function::Tan,
+        Function::Asin,
+        Function::Acos,
+        Function::Atan,
+        Function::Sinh,
+        Function::Cosh,
+        Function::Tanh,
+        Function::Ln,
+        Function::Log10,
+        Function::Log2,
+        Function::Exp,
+        Function::Sqrt,
+        Function::Cbrt,
+        Function::Abs,
+        Function::Arg,
+        Function::Conj,
+        Function::Re,
+        Function::Im,
+        Function::NCr,
+        Function::NPr,
+        Function::Gcd,
+        Function::Lcm,
+        Function::IsPrime,
+        Function::Factor,
+        Function::Fib,
+        Function::ModPow,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Asin => "asin",
+            Function::Acos => "acos",
+            Function::Atan => "atan",
+            Function::Sinh => "sinh",
+            Function::Cosh => "cosh",
+            Function::Tanh => "tanh",
+            Function::Ln => "ln",
+            Function::Log10 => "log10",
+            Function::Log2 => "log2",
+            Function::Exp => "exp",
+            Function::Sqrt => "sqrt",
+            Function::Cbrt => "cbrt",
+            Function::Abs => "abs",
+            Function::Arg => "arg",
+            Function::Conj => "conj",
+            Function::Re => "re",
+            Function::Im => "im",
+            Function::NCr => "nCr",
+            Function::NPr => "nPr",
+            Function::Gcd => "gcd",
+            Function::Lcm => "lcm",
+            Function::IsPrime => "isprime",
+            Function::Factor => "factor",
+            Function::Fib => "fib",
+            Function::ModPow => "mod_pow",
+        }
+    }
+
+    /// Number of arguments the function takes.
+    pub fn arity(self) -> usize {
+        match self {
+            Function::NCr | Function::NPr | Function::Gcd | Function::Lcm => 2,
+            Function::ModPow => 3,
+            _ => 1,
+        }
+    }
+
+    /// Looks a function up by the name used in expressions; `log` is
+    /// accepted for `log10`.
+    pub fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "log" => Some(Function::Log10),
+            _ => Function::ALL.into_iter().find(|f| f.name() == name),
+        }
+    }
+
+    /// Applies the function to its `arity()` arguments. Number theory
+    /// functions take whole numbers only.
+    pub fn call(self, args: &[Number], angle: AngleMode) -> Result<Number, CalcError> {
+        let int = |i: usize| args[i].to_bigint();
+        let result = match self {
+            Function::NCr => integers::binomial(&int(0)?, &int(1)?)?,
+            Function::NPr => integers::permutations(&int(0)?, &int(1)?)?,
+            Function::Gcd => int(0)?.gcd(&int(1)?),
+            Function::Lcm => int(0)?.lcm(&int(1)?),
+            Function::ModPow => integers::mod_pow(&int(0)?, &int(1)?, &int(2)?)?,
+            Function::IsPrime => BigInt::from(integers::is_prime(&int(0)?)? as u8),
+            // only checked here, `Calculator::format_result` shows the factors
+            Function::Factor => {
+                let n = int(0)?;
+                integers::factorize(&n)?;
+                n
+            }
+            Function::Fib => integers::fibonacci(&int(0)?)?,
+            _ => return self.apply(args[0].clone(), angle),
+        };
+        Number::integer(result)
+    }
+
+    /// Applies a function of real or complex numbers; real arguments
+    /// outside the real domain of `sqrt`, the logarithms, `asin` and `acos`
+    /// give complex results.
+    fn apply(self, x: Number, angle: AngleMode) -> Result<Number, CalcError> {
+        if let Number::Complex(z) = x {
+            return self.apply_complex(z, angle);
+        }
+        let v = x.as_f64();
+        let result = match self {
+            Function::Sin | Function::Cos | Function::Tan => return trig(self, v, angle),
+            Function::Asin | Function::Acos if !(-1.0..=1.0).contains(&v) => {
+                return self.apply_complex(Complex64::from(v), angle)
+            }
+            Function::Asin => angle.radians_to_unit(v.asin()),
+            Function::Acos => angle.radians_to_unit(v.acos()),
+            Function::Atan => angle.radians_to_unit(v.atan()),
+            Function::Sinh => v.sinh(),
+            Function::Cosh => v.cosh(),
+            Function::Tanh => v.tanh(),
+            Function::Ln | Function::Log10 | Function::Log2 if v == 0.0 => {
+                return Err(CalcError::Domain)
+            }
+            Function::Ln | Function::Log10 | Function::Log2 if v < 0.0 => {
+                return self.apply_complex(Complex64::from(v), angle)
+            }
+            Function::Ln => v.ln(),
+            Function::Log10 => v.log10(),
+            Function::Log2 => v.log2(),
+            Function::Exp => v.exp(),
+            Function::Sqrt if v < 0.0 => return self.apply_complex(Complex64::from(v), angle),
+            Function::Sqrt => v.sqrt(),
+            Function::Cbrt => v.cbrt(),
+            Function::Abs => return Ok(if v < 0.0 { x.neg() } else { x }),
+            Function::Arg if v < 0.0 => angle.radians_to_unit(PI),
+            Function::Arg | Function::Im => 0.0,
+            Function::Conj | Function::Re => return Ok(x),
+            _ => unreachable!("{} is handled by `call`", self.name()),
+        };
+        Number::float(result)
+    }
+
+    /// Principal values over the complex plane. Angles are converted like
+    /// real ones; `cbrt` gives the principal root too.
+    fn apply_complex(self, z: Complex64, angle: AngleMode) -> Result<Number, CalcError> {
+        let radians = |z: Complex64| z.scale(angle.to_radians(1.0));
+        let unit = |z: Complex64| z.scale(angle.radians_to_unit(1.0));
+        let result = match self {
+            Function::Sin => radians(z).sin(),
+            Function::Cos => radians(z).cos(),
+            Function::Tan => radians(z).tan(),
+            Function::Asin => unit(z.asin()),
+            Function::Acos => unit(z.acos()),
+            Function::Atan => unit(z.atan()),
+            Function::Sinh => z.sinh(),
+            Function::Cosh => z.cosh(),
+            Function::Tanh => z.tanh(),
+            Function::Ln => z.ln(),
+            Function::Log10 => z.log10(),
+            Function::Log2 => z.log2(),
+            Function::Exp => z.exp(),
+            Function::Sqrt => z.sqrt(),
+            Function::Cbrt => z.cbrt(),
+            Function::Abs => return Number::float(z.norm()),
+            Function::Arg => return Number::float(angle.radians_to_unit(z.arg())),
+            Function::Conj => z.conj(),
+            Function::Re => return Number::float(z.re),
+            Function::Im => return Number::float(z.im),
+            _ => unreachable!("{} is handled by `call`", self.name()),
+        };
+        Number::complex(result)
+    }
+}
+
+/// Sine, cosine and tangent. Whole quarter turns give exact results so
+/// that e.g. `sin(180)` in degrees is 0 rather than rounding noise.
+fn trig(function: Function, angle: f64, mode: AngleMode) -> Result<Number, CalcError> {
+    let quarter = mode.full_turn() / 4.0;
+    let quarters = angle / quarter;
+    if mode != AngleMode::Radians && quarters.fract() == 0.0 {
+        // sin and cos of 0°, 90°, 180°, 270°
+        let (sin, cos) = match quarters.rem_euclid(4.0) as u8 {
+            0 => (0, 1),
+            1 => (1, 0),
+            2 => (0, -1),
+            _ => (-1, 0),
+        };
+        return match function {
+            Function::Sin => Ok(Number::from(sin)),
+            Function::Cos => Ok(Number::from(cos)),
+            _ if cos == 0 => Err(CalcError::Domain),
+            _ => Ok(Number::from(sin * cos)),
+        };
+    }
+
+    let radians = mode.to_radians(angle);
+    Number::float(match function {
+        Function::Sin => radians.sin(),
+        Function::Cos => radians.cos(),
+        _ => radians.tan(),
+    })
+}
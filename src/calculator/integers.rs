@@ -0,0 +1,322 @@
+use num::bigint::BigInt;
+use num::integer::Integer;
+use num::traits::{One, Signed, ToPrimitive, Zero};
+
+use super::number::MAX_BITS;
+use super::CalcError;
+
+/// Ways to choose `k` of `n` items, zero when `k > n`.
+pub fn binomial(n: &BigInt, k: &BigInt) -> Result<BigInt, CalcError> {
+    if n.is_negative() || k.is_negative() {
+        return Err(CalcError::Domain);
+    }
+    if k > n {
+        return Ok(BigInt::zero());
+    }
+    let k = k.min(&(n - k)).clone();
+    let mut result = BigInt::one();
+    let mut i = BigInt::zero();
+    // every partial product is itself a binomial coefficient, so the
+    // division is exact
+    while i < k {
+        result = result * (n - &i) / (&i + 1);
+        if result.bits() > MAX_BITS {
+            return Err(CalcError::Overflow);
+        }
+        i += 1;
+    }
+    Ok(result)
+}
+
+/// Ordered ways to pick `k` of `n` items, zero when `k > n`.
+pub fn permutations(n: &BigInt, k: &BigInt) -> Result<BigInt, CalcError> {
+    if n.is_negative() || k.is_negative() {
+        return Err(CalcError::Domain);
+    }
+    if k > n {
+        return Ok(BigInt::zero());
+    }
+    let mut result = BigInt::one();
+    let mut factor = n.clone();
+    while factor > n - k {
+        result *= &factor;
+        if result.bits() > MAX_BITS {
+            return Err(CalcError::Overflow);
+        }
+        factor -= 1;
+    }
+    Ok(result)
+}
+
+/// `base^exponent mod modulus` without the intermediate power, in
+/// `0..modulus`.
+pub fn mod_pow(base: &BigInt, exponent: &BigInt, modulus: &BigInt) -> Result<BigInt, CalcError> {
+    if modulus.is_zero() {
+        return Err(CalcError::DivisionByZero);
+    }
+    if exponent.is_negative() || modulus.is_negative() {
+        return Err(CalcError::Domain);
+    }
+    Ok(base.mod_floor(modulus).modpow(exponent, modulus))
+}
+
+/// The `n`th Fibonacci number, `fib(0) = 0` and `fib(1) = 1`.
+pub fn fibonacci(n: &BigInt) -> Result<BigInt, CalcError> {
+    if n.is_negative() {
+        return Err(CalcError::Domain);
+    }
+    // fib(n) has about 0.69 n bits
+    let n = match n.to_u64() {
+        Some(n) if n / 10 * 7 <= MAX_BITS => n,
+        _ => return Err(CalcError::Overflow),
+    };
+    // fast doubling: fib(2k) = fib(k) (2 fib(k+1) - fib(k)) and
+    // fib(2k+1) = fib(k)² + fib(k+1)², walking the bits of n
+    let (mut a, mut b) = (BigInt::zero(), BigInt::one());
+    for bit in (0..64 - n.leading_zeros()).rev() {
+        let c = &a * (&b * 2 - &a);
+        let d = &a * &a + &b * &b;
+        (a, b) = if n >> bit & 1 == 0 {
+            (c, d)
+        } else {
+            (d.clone(), c + d)
+        };
+    }
+    Ok(a)
+}
+
+/// Whether `n` is prime. Negative numbers, 0 and 1 are not; numbers beyond
+/// `u64` are an overflow.
+pub fn is_prime(n: &BigInt) -> Result<bool, CalcError> {
+    if n.is_negative() {
+        return Ok(false);
+    }
+    n.to_u64().map(is_prime_u64).ok_or(CalcError::Overflow)
+}
+
+/// Prime factors of `n` with their powers, smallest first. Zero has none
+/// and is a domain error; the sign of `n` is ignored.
+pub fn factorize(n: &BigInt) -> Result<Vec<(u64, u32)>, CalcError> {
+    if n.is_zero() {
+        return Err(CalcError::Domain);
+    }
+    let n = n.abs().to_u64().ok_or(CalcError::Overflow)?;
+    let mut primes = vec![];
+    collect_primes(n, &mut primes);
+    primes.sort_unstable();
+
+    let mut factors: Vec<(u64, u32)> = vec![];
+    for p in primes {
+        match factors.last_mut() {
+            Some((q, power)) if *q == p => *power += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    Ok(factors)
+}
+
+/// `n` written as a product of primes such as "-2^3 × 3 × 5", itself an
+/// expression for `n`.
+pub fn factorization(n: &BigInt) -> Result<String, CalcError> {
+    let factors = factorize(n)?;
+    let mut text = if n.is_negative() { "-" } else { "" }.to_string();
+    if factors.is_empty() {
+        text.push('1');
+    }
+    for (i, (p, power)) in factors.iter().enumerate() {
+        if i > 0 {
+            text += " × ";
+        }
+        text += &p.to_string();
+        if *power > 1 {
+            text += &format!("^{}", power);
+        }
+    }
+    Ok(text)
+}
+
+/// Pushes the prime factors of `n` with repetition, in no particular order.
+fn collect_primes(mut n: u64, primes: &mut Vec<u64>) {
+    // small factors directly, the rest split by Pollard's rho
+    for p in [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37] {
+        while n.is_multiple_of(p) {
+            primes.push(p);
+            n /= p;
+        }
+    }
+    if n == 1 {
+        return;
+    }
+    if is_prime_u64(n) {
+        primes.push(n);
+        return;
+    }
+    let divisor = pollard_rho(n);
+    collect_primes(divisor, primes);
+    collect_primes(n / divisor, primes);
+}
+
+/// A non-trivial divisor of the odd composite `n`.
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1u64.. {
+        let step = |x: u64| ((mul_mod(x, x, n) as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = step(x);
+            y = step(step(y));
+            d = x.abs_diff(y).gcd(&n);
+        }
+        // a cycle without a divisor, try another polynomial
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+/// Miller-Rabin with bases that decide every `u64` exactly.
+fn is_prime_u64(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(p) = BASES.iter().find(|p| n.is_multiple_of(**p)) {
+        return n == *p;
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    'bases: for a in BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+fn mul_mod(x: u64, y: u64, n: u64) -> u64 {
+    (x as u128 * y as u128 % n as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64, n: u64) -> u64 {
+    let mut result = 1;
+    base %= n;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, n);
+        }
+        base = mul_mod(base, base, n);
+        exponent >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(n: i128) -> BigInt {
+        BigInt::from(n)
+    }
+
+    #[test]
+    fn binomials() {
+        assert_eq!(binomial(&int(10), &int(3)), Ok(int(120)));
+        assert_eq!(binomial(&int(52), &int(5)), Ok(int(2_598_960)));
+        assert_eq!(binomial(&int(5), &int(0)), Ok(int(1)));
+        assert_eq!(binomial(&int(3), &int(5)), Ok(int(0)));
+        assert_eq!(binomial(&int(-1), &int(2)), Err(CalcError::Domain));
+        assert_eq!(binomial(&int(5), &int(-1)), Err(CalcError::Domain));
+    }
+
+    #[test]
+    fn permutation_counts() {
+        assert_eq!(permutations(&int(10), &int(3)), Ok(int(720)));
+        assert_eq!(permutations(&int(5), &int(0)), Ok(int(1)));
+        assert_eq!(permutations(&int(3), &int(5)), Ok(int(0)));
+        assert_eq!(permutations(&int(-3), &int(1)), Err(CalcError::Domain));
+    }
+
+    #[test]
+    fn modular_powers() {
+        assert_eq!(mod_pow(&int(4), &int(13), &int(497)), Ok(int(445)));
+        assert_eq!(mod_pow(&int(-2), &int(3), &int(5)), Ok(int(2)));
+        assert_eq!(mod_pow(&int(7), &int(0), &int(1)), Ok(int(0)));
+        assert_eq!(
+            mod_pow(&int(2), &int(3), &int(0)),
+            Err(CalcError::DivisionByZero)
+        );
+        assert_eq!(mod_pow(&int(2), &int(-1), &int(5)), Err(CalcError::Domain));
+    }
+
+    #[test]
+    fn fibonacci_numbers() {
+        assert_eq!(fibonacci(&int(0)), Ok(int(0)));
+        assert_eq!(fibonacci(&int(1)), Ok(int(1)));
+        assert_eq!(fibonacci(&int(10)), Ok(int(55)));
+        assert_eq!(fibonacci(&int(100)), Ok(int(354_224_848_179_261_915_075)));
+        assert_eq!(fibonacci(&int(-1)), Err(CalcError::Domain));
+        assert_eq!(fibonacci(&int(1 << 40)), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn primality() {
+        let primes = [2, 3, 37, 41, 4_294_967_291, 18_446_744_073_709_551_557];
+        for p in primes {
+            assert!(is_prime_u64(p), "{}", p);
+        }
+        // Carmichael numbers, a strong pseudoprime to the bases up to 23 and
+        // the largest u64
+        let composites = [
+            0,
+            1,
+            561,
+            1105,
+            1729,
+            41_041,
+            9_746_347_772_161,
+            3_825_123_056_546_413_051,
+            u64::MAX,
+        ];
+        for n in composites {
+            assert!(!is_prime_u64(n), "{}", n);
+        }
+        assert_eq!(is_prime(&int(-7)), Ok(false));
+        assert_eq!(is_prime(&int(1 << 64)), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn factorizations() {
+        assert_eq!(factorize(&int(360)), Ok(vec![(2, 3), (3, 2), (5, 1)]));
+        assert_eq!(factorize(&int(1)), Ok(vec![]));
+        assert_eq!(factorize(&int(0)), Err(CalcError::Domain));
+        assert_eq!(factorize(&int(1 << 64)), Err(CalcError::Overflow));
+        assert_eq!(
+            factorize(&int(4_294_967_291 * 4_294_967_291)),
+            Ok(vec![(4_294_967_291, 2)])
+        );
+        assert_eq!(
+            factorize(&int(4_294_967_279 * 4_294_967_291)),
+            Ok(vec![(4_294_967_279, 1), (4_294_967_291, 1)])
+        );
+        assert_eq!(
+            factorize(&int(18_446_744_073_709_551_557)),
+            Ok(vec![(18_446_744_073_709_551_557, 1)])
+        );
+        for n in 2..20_000u64 {
+            let factors = factorize(&BigInt::from(n)).unwrap();
+            assert!(factors.iter().all(|(p, _)| is_prime_u64(*p)), "{}", n);
+            let product: u64 = factors.iter().map(|(p, power)| p.pow(*power)).product();
+            assert_eq!(product, n);
+        }
+        assert_eq!(factorization(&int(-120)).unwrap(), "-2^3 × 3 × 5");
+        assert_eq!(factorization(&int(1)).unwrap(), "1");
+    }
+}
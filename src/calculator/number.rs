@@ -0,0 +1,670 @@
+use std::fmt;
+
+use num::bigint::BigInt;
+use num::complex::Complex64;
+use num::integer::Integer;
+use num::rational::BigRational;
+use num::traits::{One, Pow, Signed, ToPrimitive, Zero};
+
+use super::CalcError;
+
+/// Significant digits shown for float results; enough to hide the binary
+/// representation noise of `f64`.
+const DISPLAY_DIGITS: i32 = 12;
+
+/// How `mod` and `div` treat negative operands.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum DivisionMode {
+    /// Quotient rounded toward zero, remainder takes the sign of the
+    /// dividend: `-7 mod 2 = -1`.
+    Truncated,
+    /// Remainder is never negative: `-7 mod 2 = 1`, `-7 div 2 = -4`.
+    #[default]
+    Euclidean,
+}
+
+/// How exact results are written in fraction mode.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum FractionDisplay {
+    /// `3/2`
+    #[default]
+    Improper,
+    /// `1 1/2`
+    Mixed,
+    /// `1.5`, non-terminating expansions rounded.
+    Decimal,
+}
+
+impl FractionDisplay {
+    pub const ALL: [FractionDisplay; 3] = [
+        FractionDisplay::Improper,
+        FractionDisplay::Mixed,
+        FractionDisplay::Decimal,
+    ];
+
+    pub fn next(self) -> FractionDisplay {
+        match self {
+            FractionDisplay::Improper => FractionDisplay::Mixed,
+            FractionDisplay::Mixed => FractionDisplay::Decimal,
+            FractionDisplay::Decimal => FractionDisplay::Improper,
+        }
+    }
+}
+
+impl fmt::Display for FractionDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FractionDisplay::Improper => write!(f, "improper"),
+            FractionDisplay::Mixed => write!(f, "mixed"),
+            FractionDisplay::Decimal => write!(f, "decimal"),
+        }
+    }
+}
+
+/// How complex results are written.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ComplexDisplay {
+    /// `3+4i`
+    #[default]
+    Rectangular,
+    /// `5∠0.927295218002`, the angle in the current angle unit.
+    Polar,
+}
+
+impl fmt::Display for ComplexDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComplexDisplay::Rectangular => write!(f, "rect"),
+            ComplexDisplay::Polar => write!(f, "polar"),
+        }
+    }
+}
+
+/// Decimal places kept by divisions and negative powers, like `scale` in
+/// bc.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Precision(pub u32);
+
+impl Default for Precision {
+    fn default() -> Self {
+        Precision(20)
+    }
+}
+
+/// Largest numerator or denominator, in bits, an exact result may have
+/// before it counts as an overflow (about 30 000 decimal digits).
+pub(super) const MAX_BITS: u64 = 100_000;
+
+/// Non-terminating fractions are shown with this many decimal places.
+const DISPLAY_DECIMALS: usize = 20;
+
+/// Numeric value carried through the engine. Integers and decimals stay
+/// exact with any number of digits; results of irrational functions
+/// become floats.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Number {
+    Exact(BigRational),
+    Float(f64),
+    /// Always has a non-zero imaginary part; see `Number::complex`.
+    Complex(Complex64),
+}
+
+impl Default for Number {
+    fn default() -> Self {
+        Number::Exact(BigRational::zero())
+    }
+}
+
+impl From<i128> for Number {
+    fn from(n: i128) -> Self {
+        Number::Exact(BigRational::from_integer(n.into()))
+    }
+}
+
+impl Number {
+    /// Parses a number literal such as "12", "-3.", "0.25" or "1e-3"
+    /// exactly; only exponents beyond ±10000 fall back to floats.
+    pub fn parse(input: &str) -> Option<Number> {
+        let (mantissa, exponent) = match input.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (input, 0),
+        };
+        if exponent.abs() > 10_000 {
+            return Number::float(input.parse().ok()?).ok();
+        }
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", whole, fraction);
+        if digits.trim_start_matches(['-', '+']).is_empty() {
+            return None;
+        }
+        let numer: BigInt = digits.parse().ok()?;
+        let scale = exponent - fraction.len() as i64;
+        let ten = BigRational::from_integer(10.into());
+        Some(Number::Exact(
+            BigRational::from_integer(numer) * ten.pow(scale as i32),
+        ))
+    }
+
+    /// The imaginary unit `i`.
+    pub const I: Number = Number::Complex(Complex64::new(0.0, 1.0));
+
+    /// The real value as a float, NaN for complex numbers.
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Number::Exact(r) => r.to_f64().unwrap_or(f64::NAN),
+            Number::Float(x) => *x,
+            Number::Complex(_) => f64::NAN,
+        }
+    }
+
+    pub fn as_complex(&self) -> Complex64 {
+        match self {
+            Number::Complex(z) => *z,
+            n => Complex64::from(n.as_f64()),
+        }
+    }
+
+    pub fn is_complex(&self) -> bool {
+        matches!(self, Number::Complex(_))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Exact(r) => r.is_zero(),
+            Number::Float(x) => *x == 0.0,
+            Number::Complex(_) => false,
+        }
+    }
+
+    /// The value as a whole number; fractions are a domain error and
+    /// numbers beyond `i128` an overflow.
+    pub fn to_int(&self) -> Result<i128, CalcError> {
+        let exact = self.to_exact()?;
+        if !exact.is_integer() {
+            return Err(CalcError::Domain);
+        }
+        exact.numer().to_i128().ok_or(CalcError::Overflow)
+    }
+
+    /// The value as a whole number of any size; fractions are a domain
+    /// error.
+    pub fn to_bigint(&self) -> Result<BigInt, CalcError> {
+        let exact = self.to_exact()?;
+        if !exact.is_integer() {
+            return Err(CalcError::Domain);
+        }
+        Ok(exact.to_integer())
+    }
+
+    /// The low 64 bits of the value truncated toward zero, in two's
+    /// complement.
+    pub fn low_bits(&self) -> i128 {
+        let whole = match self.to_exact() {
+            Ok(r) => r.trunc().to_integer(),
+            Err(_) => BigInt::zero(),
+        };
+        (whole & BigInt::from(u64::MAX)).to_i128().unwrap()
+    }
+
+    fn to_exact(&self) -> Result<BigRational, CalcError> {
+        match self {
+            Number::Exact(r) => Ok(r.clone()),
+            Number::Float(x) => BigRational::from_float(*x).ok_or(CalcError::Domain),
+            Number::Complex(_) => Err(CalcError::Domain),
+        }
+    }
+
+    pub fn neg(&self) -> Number {
+        match self {
+            Number::Exact(r) => Number::Exact(-r),
+            Number::Float(x) => Number::Float(-x),
+            Number::Complex(z) => Number::Complex(-z),
+        }
+    }
+
+    pub fn add(&self, rhs: &Number) -> Result<Number, CalcError> {
+        match (self, rhs) {
+            (Number::Exact(x), Number::Exact(y)) => exact(x + y),
+            (x, y) if x.is_complex() || y.is_complex() => {
+                Number::complex(x.as_complex() + y.as_complex())
+            }
+            (x, y) => Number::float(x.as_f64() + y.as_f64()),
+        }
+    }
+
+    pub fn sub(&self, rhs: &Number) -> Result<Number, CalcError> {
+        match (self, rhs) {
+            (Number::Exact(x), Number::Exact(y)) => exact(x - y),
+            (x, y) if x.is_complex() || y.is_complex() => {
+                Number::complex(x.as_complex() - y.as_complex())
+            }
+            (x, y) => Number::float(x.as_f64() - y.as_f64()),
+        }
+    }
+
+    pub fn mul(&self, rhs: &Number) -> Result<Number, CalcError> {
+        match (self, rhs) {
+            (Number::Exact(x), Number::Exact(y)) => exact(x * y),
+            (x, y) if x.is_complex() || y.is_complex() => {
+                Number::complex(x.as_complex() * y.as_complex())
+            }
+            (x, y) => Number::float(x.as_f64() * y.as_f64()),
+        }
+    }
+
+    /// Exact quotient; callers round it to the wanted precision.
+    pub fn div(&self, rhs: &Number) -> Result<Number, CalcError> {
+        if rhs.is_zero() {
+            return Err(CalcError::DivisionByZero);
+        }
+        match (self, rhs) {
+            (Number::Exact(x), Number::Exact(y)) => exact(x / y),
+            (x, y) if x.is_complex() || y.is_complex() => {
+                Number::complex(x.as_complex() / y.as_complex())
+            }
+            (x, y) => Number::float(x.as_f64() / y.as_f64()),
+        }
+    }
+
+    /// Remainder of the division by `rhs`.
+    pub fn modulo(&self, rhs: &Number, mode: DivisionMode) -> Result<Number, CalcError> {
+        let quotient = self.int_div(rhs, mode)?;
+        match (self, rhs, mode) {
+            (Number::Exact(_), Number::Exact(_), _) => self.sub(&quotient.mul(rhs)?),
+            (x, y, DivisionMode::Truncated) => Number::float(x.as_f64() % y.as_f64()),
+            (x, y, DivisionMode::Euclidean) => Number::float(x.as_f64().rem_euclid(y.as_f64())),
+        }
+    }
+
+    /// Whole quotient of the division by `rhs`, consistent with `modulo`.
+    pub fn int_div(&self, rhs: &Number, mode: DivisionMode) -> Result<Number, CalcError> {
+        if rhs.is_zero() {
+            return Err(CalcError::DivisionByZero);
+        }
+        match (self, rhs, mode) {
+            (Number::Exact(x), Number::Exact(y), DivisionMode::Truncated) => exact((x / y).trunc()),
+            (Number::Exact(x), Number::Exact(y), DivisionMode::Euclidean) => {
+                let quotient = x / y;
+                exact(if y.is_positive() {
+                    quotient.floor()
+                } else {
+                    quotient.ceil()
+                })
+            }
+            (x, y, DivisionMode::Truncated) => Number::float((x.as_f64() / y.as_f64()).trunc()),
+            (x, y, DivisionMode::Euclidean) => Number::float(x.as_f64().div_euclid(y.as_f64())),
+        }
+    }
+
+    /// `n!` for whole numbers, as long as the result stays within the size
+    /// limit, and `Γ(x + 1)` as a float for other real numbers.
+    pub fn factorial(&self) -> Result<Number, CalcError> {
+        let n = match self.to_exact() {
+            Ok(n) if n.is_integer() && !n.is_negative() => n.to_integer(),
+            Ok(n) if !n.is_integer() => return Number::float(gamma(self.as_f64() + 1.0)),
+            _ => return Err(CalcError::Domain),
+        };
+        let mut product = BigInt::one();
+        let mut k = BigInt::one();
+        while k <= n {
+            product *= &k;
+            if product.bits() > MAX_BITS {
+                return Err(CalcError::Overflow);
+            }
+            k += 1;
+        }
+        Ok(Number::Exact(BigRational::from_integer(product)))
+    }
+
+    /// Integer powers stay exact; fractional exponents give floats, or the
+    /// principal complex root of a negative base.
+    pub fn pow(&self, rhs: &Number) -> Result<Number, CalcError> {
+        if self.is_zero() && rhs.as_f64() < 0.0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        match (self, rhs) {
+            (Number::Exact(x), Number::Exact(y)) if y.is_integer() => {
+                let bits = (x.numer().bits().max(x.denom().bits()) as f64) * y.to_f64().unwrap();
+                match y.to_integer().to_i32() {
+                    Some(y) if bits.abs() <= MAX_BITS as f64 => exact(x.pow(y)),
+                    // 0 and ±1 stay put whatever the exponent, up to the
+                    // sign that odd exponents keep
+                    _ if x.abs().is_one() || x.is_zero() => exact(if y.to_integer().is_odd() {
+                        x.clone()
+                    } else {
+                        x.abs()
+                    }),
+                    _ => Err(CalcError::Overflow),
+                }
+            }
+            (x, y)
+                if x.is_complex()
+                    || y.is_complex()
+                    || (x.as_f64() < 0.0 && y.as_f64().fract() != 0.0) =>
+            {
+                if x.is_zero() {
+                    return Err(CalcError::Domain);
+                }
+                // whole exponents by repeated multiplication, so i^2 is -1
+                match y.to_int().ok().and_then(|n| i32::try_from(n).ok()) {
+                    Some(n) => Number::complex(x.as_complex().powi(n)),
+                    None => Number::complex(x.as_complex().powc(y.as_complex())),
+                }
+            }
+            (x, y) => Number::float(x.as_f64().powf(y.as_f64())),
+        }
+    }
+
+    /// Rounds an exact value whose decimal expansion never ends half away
+    /// from zero to `places` decimals; other values are kept as they are.
+    pub fn limit(self, Precision(places): Precision) -> Number {
+        match self {
+            Number::Exact(r) if terminating_places(&r).is_none() => {
+                let scale = BigRational::from_integer(10.into()).pow(places as i32);
+                Number::Exact((r * &scale).round() / scale)
+            }
+            n => n,
+        }
+    }
+
+    /// Writes the value in the given fraction form; floats and integers
+    /// look the same in every form.
+    pub fn to_fraction(&self, form: FractionDisplay) -> String {
+        let r = match self {
+            Number::Exact(r) if !r.is_integer() => r,
+            n => return n.to_string(),
+        };
+        match form {
+            FractionDisplay::Improper => format!("{}/{}", r.numer(), r.denom()),
+            FractionDisplay::Mixed => {
+                let whole = r.trunc().to_integer();
+                let rest = (r - r.trunc()).abs();
+                if whole.is_zero() {
+                    format!("{}/{}", r.numer(), r.denom())
+                } else {
+                    format!("{} {}/{}", whole, rest.numer(), rest.denom())
+                }
+            }
+            FractionDisplay::Decimal => self.to_string(),
+        }
+    }
+
+    /// Wraps a complex result, rejecting infinite and NaN parts. Values on
+    /// the real axis become floats.
+    pub(crate) fn complex(z: Complex64) -> Result<Number, CalcError> {
+        if z.is_nan() {
+            Err(CalcError::Domain)
+        } else if z.is_infinite() {
+            Err(CalcError::Overflow)
+        } else if z.im == 0.0 {
+            Ok(Number::Float(z.re))
+        } else {
+            Ok(Number::Complex(z))
+        }
+    }
+
+    /// Wraps a whole number result, rejecting ones too large to work with.
+    pub(crate) fn integer(n: BigInt) -> Result<Number, CalcError> {
+        exact(BigRational::from_integer(n))
+    }
+
+    /// Wraps a float result, rejecting infinities and NaN.
+    pub(crate) fn float(x: f64) -> Result<Number, CalcError> {
+        if x.is_nan() {
+            Err(CalcError::Domain)
+        } else if x.is_infinite() {
+            Err(CalcError::Overflow)
+        } else {
+            Ok(Number::Float(x))
+        }
+    }
+}
+
+/// The gamma function by the Lanczos approximation (g = 7), good to
+/// about 15 significant digits.
+fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // reflection formula
+        return std::f64::consts::PI / ((std::f64::consts::PI * x).sin() * gamma(1.0 - x));
+    }
+    if x > 171.7 {
+        // beyond the largest float
+        return f64::INFINITY;
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+    (2.0 * std::f64::consts::PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * sum
+}
+
+/// Wraps an exact result, rejecting ones too large to work with.
+fn exact(r: BigRational) -> Result<Number, CalcError> {
+    if r.numer().bits() > MAX_BITS || r.denom().bits() > MAX_BITS {
+        Err(CalcError::Overflow)
+    } else {
+        Ok(Number::Exact(r))
+    }
+}
+
+/// Decimal expansion of `r` rounded to `places` digits after the point.
+fn decimal(r: &BigRational, places: usize) -> String {
+    let scale = BigInt::from(10).pow(places as u32);
+    let scaled = (r * BigRational::from_integer(scale)).round().to_integer();
+    let digits = format!("{:0>width$}", scaled.abs(), width = places + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - places);
+    let sign = if scaled.is_negative() { "-" } else { "" };
+    format!("{}{}.{}", sign, whole, fraction)
+}
+
+/// Digits after the point needed to write `r` exactly, `None` if its
+/// expansion never ends.
+fn terminating_places(r: &BigRational) -> Option<usize> {
+    let mut denom = r.denom().clone();
+    let mut places = [0usize; 2];
+    for (count, factor) in places.iter_mut().zip([2u32, 5]) {
+        while (&denom % factor).is_zero() {
+            denom /= factor;
+            *count += 1;
+        }
+    }
+    denom.is_one().then(|| places[0].max(places[1]))
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Exact(r) if r.is_integer() => write!(f, "{}", r.numer()),
+            Number::Exact(r) => {
+                let places = terminating_places(r).unwrap_or(DISPLAY_DECIMALS);
+                write!(f, "{}", trim_fraction(&decimal(r, places)))
+            }
+            Number::Complex(z) => {
+                let im = match z.im.abs() {
+                    1.0 => String::new(),
+                    im => Number::Float(im).to_string(),
+                };
+                let sign = if z.im < 0.0 { "-" } else { "+" };
+                if z.re == 0.0 {
+                    write!(f, "{}{}i", sign.trim_start_matches('+'), im)
+                } else {
+                    write!(f, "{}{}{}i", Number::Float(z.re), sign, im)
+                }
+            }
+            Number::Float(x) if !x.is_finite() => write!(f, "{}", x),
+            Number::Float(x) if *x == 0.0 => write!(f, "0"),
+            Number::Float(x) => {
+                let x = *x;
+                let magnitude = x.abs().log10().floor() as i32;
+                if !(-6..15).contains(&magnitude) {
+                    let text = format!("{:.*e}", (DISPLAY_DIGITS - 1) as usize, x);
+                    let (mantissa, exponent) = text.split_once('e').unwrap();
+                    return write!(f, "{}e{}", trim_fraction(mantissa), exponent);
+                }
+                let decimals = (DISPLAY_DIGITS - 1 - magnitude).max(0) as usize;
+                write!(f, "{}", trim_fraction(&format!("{:.*}", decimals, x)))
+            }
+        }
+    }
+}
+
+/// Drops trailing zeros (and a dangling point) from a fixed-point string.
+fn trim_fraction(text: &str) -> &str {
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `x mod y` and `x div y` for `±7 ±2`, or `±7.5 ±2` as floats.
+    fn divisions(mode: DivisionMode, float: bool) -> Vec<(Number, Number)> {
+        let mut results = vec![];
+        for (x, y) in [(7, 2), (-7, 2), (7, -2), (-7, -2)] {
+            let (x, y) = if float {
+                (
+                    Number::Float(x.signum() as f64 * 7.5),
+                    Number::Float(y as f64),
+                )
+            } else {
+                (Number::from(x), Number::from(y))
+            };
+            results.push((x.modulo(&y, mode).unwrap(), x.int_div(&y, mode).unwrap()));
+        }
+        results
+    }
+
+    fn exact(pairs: [(i128, i128); 4]) -> Vec<(Number, Number)> {
+        pairs
+            .into_iter()
+            .map(|(m, d)| (Number::from(m), Number::from(d)))
+            .collect()
+    }
+
+    fn float(pairs: [(f64, f64); 4]) -> Vec<(Number, Number)> {
+        pairs
+            .into_iter()
+            .map(|(m, d)| (Number::Float(m), Number::Float(d)))
+            .collect()
+    }
+
+    #[test]
+    fn truncated_division() {
+        assert_eq!(
+            divisions(DivisionMode::Truncated, false),
+            exact([(1, 3), (-1, -3), (1, -3), (-1, 3)])
+        );
+        assert_eq!(
+            divisions(DivisionMode::Truncated, true),
+            float([(1.5, 3.0), (-1.5, -3.0), (1.5, -3.0), (-1.5, 3.0)])
+        );
+    }
+
+    #[test]
+    fn euclidean_division() {
+        assert_eq!(
+            divisions(DivisionMode::Euclidean, false),
+            exact([(1, 3), (1, -4), (1, -3), (1, 4)])
+        );
+        assert_eq!(
+            divisions(DivisionMode::Euclidean, true),
+            float([(1.5, 3.0), (0.5, -4.0), (1.5, -3.0), (0.5, 4.0)])
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        for mode in [DivisionMode::Truncated, DivisionMode::Euclidean] {
+            for x in [Number::from(7), Number::Float(7.5)] {
+                for zero in [Number::from(0), Number::Float(0.0)] {
+                    assert_eq!(x.modulo(&zero, mode), Err(CalcError::DivisionByZero));
+                    assert_eq!(x.int_div(&zero, mode), Err(CalcError::DivisionByZero));
+                }
+            }
+        }
+    }
+
+    fn ratio(numer: i128, denom: i128) -> Number {
+        Number::Exact(BigRational::new(numer.into(), denom.into()))
+    }
+
+    #[test]
+    fn parse_literals() {
+        assert_eq!(Number::parse("12"), Some(Number::from(12)));
+        assert_eq!(Number::parse("-3."), Some(Number::from(-3)));
+        assert_eq!(Number::parse(".5"), Some(ratio(1, 2)));
+        assert_eq!(Number::parse("-.25"), Some(ratio(-1, 4)));
+        assert_eq!(Number::parse("0.1"), Some(ratio(1, 10)));
+        assert_eq!(Number::parse("1e-3"), Some(ratio(1, 1000)));
+        assert_eq!(Number::parse("2.5E2"), Some(Number::from(250)));
+        assert_eq!(Number::parse("1e+2"), Some(Number::from(100)));
+        assert_eq!(Number::parse("1e-20000"), Some(Number::Float(0.0)));
+        for invalid in ["", ".", "-", "e5", "1e", "1.2.3", "1e2.5", "1e20000", "x"] {
+            assert_eq!(Number::parse(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn limit_rounds_only_endless_expansions() {
+        let places = Precision(3);
+        assert_eq!(ratio(2, 3).limit(places), ratio(667, 1000));
+        assert_eq!(ratio(-2, 3).limit(places), ratio(-667, 1000));
+        assert_eq!(ratio(1, 6).limit(Precision(2)), ratio(17, 100));
+        assert_eq!(ratio(1, 3).limit(Precision(0)), Number::from(0));
+        assert_eq!(ratio(1, 1024).limit(places), ratio(1, 1024));
+        assert_eq!(
+            Number::Float(0.123456).limit(places),
+            Number::Float(0.123456)
+        );
+    }
+
+    #[test]
+    fn results_beyond_the_size_limit_overflow() {
+        let two = Number::from(2);
+        let power = |n: i128| two.pow(&Number::from(n));
+        let quarter = power(MAX_BITS as i128 / 4).unwrap();
+        assert_eq!(power(MAX_BITS as i128 + 1), Err(CalcError::Overflow));
+        assert_eq!(power(-(MAX_BITS as i128) - 1), Err(CalcError::Overflow));
+        // 2^(MAX_BITS / 2) fits, 2^MAX_BITS takes one bit more than allowed
+        let half = quarter.mul(&quarter).unwrap();
+        assert_eq!(half.mul(&half), Err(CalcError::Overflow));
+        assert_eq!(
+            Number::from(1).div(&half).unwrap().div(&half),
+            Err(CalcError::Overflow)
+        );
+    }
+
+    #[test]
+    fn display_trims_trailing_zeros() {
+        assert_eq!(Number::from(100).to_string(), "100");
+        assert_eq!(ratio(1, 4).to_string(), "0.25");
+        assert_eq!(ratio(-1, 2).to_string(), "-0.5");
+        assert_eq!(ratio(1, 3).to_string(), "0.33333333333333333333");
+        assert_eq!(ratio(2, 3).to_string(), "0.66666666666666666667");
+        assert_eq!(Number::Float(0.1 + 0.2).to_string(), "0.3");
+        assert_eq!(Number::Float(2.0).to_string(), "2");
+        assert_eq!(Number::Float(-0.0).to_string(), "0");
+        assert_eq!(Number::Float(1e20).to_string(), "1e20");
+        assert_eq!(Number::Float(1.5e-7).to_string(), "1.5e-7");
+        assert_eq!(Number::Float(f64::INFINITY).to_string(), "inf");
+    }
+}
@@ -0,0 +1,528 @@
+use std::fmt;
+
+use num::complex::Complex64;
+
+use super::{is_currency_code, Constant, Function, Number, Tokens, Unit};
+
+/// Why a piece of text is not a valid expression.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseErrorKind {
+    UnexpectedCharacter(char),
+    InvalidNumber,
+    /// A number or an opening paren was expected.
+    ExpectedOperand,
+    /// An operator or a closing paren was expected.
+    ExpectedOperator,
+    UnmatchedCloseParen,
+    UnclosedParen,
+    /// A function name must be followed by its argument in parens.
+    ExpectedCallParen,
+    /// `=` must follow a lone variable name or a function head such as
+    /// `f(x, y)` at the start.
+    InvalidAssignment,
+    /// Built-in constants such as `c` cannot be assigned to.
+    ConstantAssignment(String),
+}
+
+/// Parse failure with the character offset it was detected at.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub position: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    fn new(position: usize, kind: ParseErrorKind) -> Self {
+        Self { position, kind }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c)?,
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number")?,
+            ParseErrorKind::ExpectedOperand => write!(f, "expected a number")?,
+            ParseErrorKind::ExpectedOperator => write!(f, "expected an operator")?,
+            ParseErrorKind::UnmatchedCloseParen => write!(f, "unmatched ')'")?,
+            ParseErrorKind::UnclosedParen => write!(f, "unclosed '('")?,
+            ParseErrorKind::ExpectedCallParen => write!(f, "expected '(' after function name")?,
+            ParseErrorKind::InvalidAssignment => {
+                write!(f, "can only assign to a name or define a function")?
+            }
+            ParseErrorKind::ConstantAssignment(name) => {
+                write!(f, "`{}` is a built-in constant", name)?
+            }
+        }
+        write!(f, " at position {}", self.position + 1)
+    }
+}
+
+/// Splits `input` into tokens paired with their character offsets.
+fn tokenize(input: &str) -> Result<Vec<(usize, Tokens)>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = vec![];
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        let token = match c {
+            c if c.is_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            '0' if matches!(chars.get(pos + 1), Some('x' | 'b' | 'o')) => {
+                let start = pos;
+                let radix = match chars[pos + 1] {
+                    'x' => 16,
+                    'b' => 2,
+                    _ => 8,
+                };
+                pos += 2;
+                while pos < chars.len() && chars[pos].is_ascii_alphanumeric() {
+                    pos += 1;
+                }
+                let digits: String = chars[start + 2..pos].iter().collect();
+                let number = i128::from_str_radix(&digits, radix)
+                    .map_err(|_| ParseError::new(start, ParseErrorKind::InvalidNumber))?;
+                tokens.push((start, Tokens::Number(Number::from(number))));
+                continue;
+            }
+            '0'..='9' | '.' => {
+                let start = pos;
+                pos = scan_number(&chars, pos);
+                let text: String = chars[start..pos].iter().collect();
+                let mut number = Number::parse(&text)
+                    .ok_or_else(|| ParseError::new(start, ParseErrorKind::InvalidNumber))?;
+                // imaginary literal such as "4i" or "2.5i"
+                if chars.get(pos) == Some(&'i')
+                    && !chars
+                        .get(pos + 1)
+                        .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+                {
+                    number = Number::Complex(Complex64::new(0.0, number.as_f64()));
+                    pos += 1;
+                }
+                tokens.push((start, Tokens::Number(number)));
+                continue;
+            }
+            c if c.is_alphabetic() => {
+                let start = pos;
+                while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+                    pos += 1;
+                }
+                let name: String = chars[start..pos].iter().collect();
+                if let Some(unit) = unit_context(&tokens)
+                    .then(|| Unit::from_name(&name))
+                    .flatten()
+                {
+                    tokens.push((start, Tokens::Unit(unit)));
+                    continue;
+                }
+                if unit_context(&tokens) && is_currency_code(&name) {
+                    tokens.push((start, Tokens::Currency(name)));
+                    continue;
+                }
+                let token = match name.as_str() {
+                    "ans" => Tokens::Ans,
+                    "i" => Tokens::Number(Number::I),
+                    "mod" => Tokens::Mod,
+                    "div" => Tokens::IntDiv,
+                    "and" => Tokens::And,
+                    "or" => Tokens::Or,
+                    "xor" => Tokens::Xor,
+                    "not" => Tokens::Not,
+                    "shl" => Tokens::Shl,
+                    "shr" => Tokens::Shr,
+                    "rol" => Tokens::Rol,
+                    "ror" => Tokens::Ror,
+                    "to" | "in" => Tokens::Convert,
+                    // `50% of 200`
+                    "of" if matches!(tokens.last(), Some((_, Tokens::Percent))) => Tokens::Mul,
+                    _ => match (Function::from_name(&name), Constant::from_name(&name)) {
+                        (Some(func), _) => Tokens::Function(func),
+                        // a name right before a paren calls a user function,
+                        // which may take the name of a constant
+                        (None, _) if chars.get(pos) == Some(&'(') => Tokens::Call(name, 0),
+                        (None, Some(_)) if tokens.is_empty() && assign_follows(&chars[pos..]) => {
+                            return Err(ParseError::new(
+                                start,
+                                ParseErrorKind::ConstantAssignment(name),
+                            ));
+                        }
+                        (None, Some(constant)) => {
+                            // `2 pi` multiplies, as a paren group after an
+                            // operand does
+                            if matches!(
+                                tokens.last(),
+                                Some((
+                                    _,
+                                    Tokens::Number(_)
+                                        | Tokens::Ans
+                                        | Tokens::Variable(_)
+                                        | Tokens::CloseParen
+                                        | Tokens::Factorial
+                                        | Tokens::Percent
+                                ))
+                            ) {
+                                tokens.push((start, Tokens::Mul));
+                            }
+                            Tokens::Number(constant.value())
+                        }
+                        (None, None) => Tokens::Variable(name),
+                    },
+                };
+                tokens.push((start, token));
+                continue;
+            }
+            '+' => Tokens::Add,
+            '-' | '−' => Tokens::Sub,
+            '*' | '×' => Tokens::Mul,
+            '/' if chars.get(pos + 1) == Some(&'/') => {
+                tokens.push((pos, Tokens::IntDiv));
+                pos += 2;
+                continue;
+            }
+            '/' | '÷' => Tokens::Div,
+            '<' | '>' if chars.get(pos + 1) == Some(&c) => {
+                let shift = if c == '<' { Tokens::Shl } else { Tokens::Shr };
+                tokens.push((pos, shift));
+                pos += 2;
+                continue;
+            }
+            '!' => Tokens::Factorial,
+            '%' if percent_follows(&chars[pos + 1..]) => Tokens::Percent,
+            '%' => Tokens::Mod,
+            '^' => Tokens::Pow,
+            '&' => Tokens::And,
+            '|' => Tokens::Or,
+            '~' => Tokens::Not,
+            '=' => Tokens::Assign,
+            ',' => Tokens::Comma,
+            '(' => Tokens::OpenParen,
+            ')' => Tokens::CloseParen,
+            c => return Err(ParseError::new(pos, ParseErrorKind::UnexpectedCharacter(c))),
+        };
+        tokens.push((pos, token));
+        pos += 1;
+    }
+
+    Ok(tokens)
+}
+
+/// Whether a name read next is looked up as a unit first: right after an
+/// operand, another unit or `to`, and in the denominator of a unit such as
+/// `km/h`. Elsewhere `g` or `h` are constants and `m` can be a variable.
+fn unit_context(tokens: &[(usize, Tokens)]) -> bool {
+    match tokens {
+        [.., (_, Tokens::Unit(_) | Tokens::Currency(_)), (_, Tokens::Div)] => true,
+        [.., (_, last)] => matches!(
+            last,
+            Tokens::Number(_)
+                | Tokens::Ans
+                | Tokens::Variable(_)
+                | Tokens::CloseParen
+                | Tokens::Unit(_)
+                | Tokens::Currency(_)
+                | Tokens::Convert
+        ),
+        [] => false,
+    }
+}
+
+/// Whether a `%` followed by `rest` is a percent sign rather than `mod`,
+/// which is the case unless an operand comes next: `10%`, `200 + 10% - 5`
+/// and `50% of 200` but `7 % 3` and `7 % -3`. A sign counts as part of the
+/// operand when it is spaced off the `%` and written against the number.
+fn percent_follows(rest: &[char]) -> bool {
+    let starts_operand = |c: char| c.is_ascii_digit() || matches!(c, '.' | '(' | '~');
+    let start = match rest.iter().position(|c| !c.is_whitespace()) {
+        Some(start) => start,
+        None => return true,
+    };
+    let c = rest[start];
+    if matches!(c, '-' | '−' | '+') && start > 0 {
+        return !rest
+            .get(start + 1)
+            .is_some_and(|next| starts_operand(*next) || next.is_alphabetic());
+    }
+    if !c.is_alphabetic() {
+        return !starts_operand(c);
+    }
+    let word: String = rest[start..]
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .collect();
+    matches!(
+        word.as_str(),
+        "of" | "mod" | "div" | "and" | "or" | "xor" | "shl" | "shr" | "rol" | "ror" | "to" | "in"
+    )
+}
+
+/// Returns the end of the number literal starting at `start`, which may
+/// carry a fraction and an exponent: "12", "0.5", ".5", "1.5e-3".
+fn scan_number(chars: &[char], start: usize) -> usize {
+    let digits = |mut pos: usize| {
+        while pos < chars.len() && chars[pos].is_ascii_digit() {
+            pos += 1;
+        }
+        pos
+    };
+
+    let mut pos = digits(start);
+    if chars.get(pos) == Some(&'.') {
+        pos = digits(pos + 1);
+    }
+    if matches!(chars.get(pos), Some('e' | 'E')) {
+        let sign = usize::from(matches!(chars.get(pos + 1), Some('+' | '-')));
+        if chars.get(pos + 1 + sign).is_some_and(char::is_ascii_digit) {
+            pos = digits(pos + 1 + sign);
+        }
+    }
+    pos
+}
+
+/// Turns an expression such as "3*(4+2)/7" into the infix token stream
+/// `Calculator` builds from key presses. Unary minus becomes `Tokens::Neg`
+/// and a paren group directly following an operand multiplies it. Integer
+/// literals may be written in hex, binary or octal as `0xFF`, `0b101` or
+/// `0o17`; `i` is the imaginary unit and `4i` an imaginary literal.
+/// Constants such as `pi` or `c` become their values, multiplying an
+/// operand right before them as in `2pi`, and unit names after a number
+/// make a quantity: `5 km to mi`. A fraction of two numbers written
+/// without spaces takes the unit as a whole, so `1/3 km` is a third of a
+/// kilometre while `5 km / 2 h` divides by `2 h`. Other names are
+/// variables, assigned to with `name = expression`, or calls of user
+/// functions defined with `f(x, y) = expression`.
+pub fn parse(input: &str) -> Result<Vec<Tokens>, ParseError> {
+    let mut tokens = vec![];
+    // positions of the open parens, and whether each starts the argument
+    // list of a call
+    let mut open_parens: Vec<(usize, bool)> = vec![];
+    // true while a number or an opening paren is required
+    let mut expect_operand = true;
+    let chars: Vec<char> = input.chars().collect();
+    // index of the last `/` written without spaces around it
+    let mut tight_div = None;
+
+    for (pos, token) in tokenize(input)? {
+        if matches!(tokens.last(), Some(Tokens::Function(_))) && token != Tokens::OpenParen {
+            return Err(ParseError::new(pos, ParseErrorKind::ExpectedCallParen));
+        }
+        if expect_operand {
+            match token {
+                Tokens::Number(_)
+                | Tokens::Ans
+                | Tokens::Variable(_)
+                | Tokens::Unit(_)
+                | Tokens::Currency(_) => expect_operand = false,
+                Tokens::OpenParen => {
+                    let call =
+                        matches!(tokens.last(), Some(Tokens::Function(_) | Tokens::Call(..)));
+                    open_parens.push((pos, call));
+                }
+                Tokens::Function(_) | Tokens::Call(..) => {}
+                Tokens::Sub => {
+                    tokens.push(Tokens::Neg);
+                    continue;
+                }
+                Tokens::Not => {}
+                Tokens::Add => continue,
+                _ => return Err(ParseError::new(pos, ParseErrorKind::ExpectedOperand)),
+            }
+        } else {
+            match token {
+                Tokens::Number(_) | Tokens::Ans | Tokens::Variable(_)
+                    if tokens.last() == Some(&Tokens::CloseParen) =>
+                {
+                    tokens.push(Tokens::Mul);
+                }
+                Tokens::Unit(_) | Tokens::Currency(_) => {
+                    let n = tokens.len();
+                    let fraction = n >= 3
+                        && tight_div == Some(n - 2)
+                        && matches!(tokens[n - 3], Tokens::Number(_))
+                        && matches!(tokens[n - 1], Tokens::Number(_))
+                        && !matches!(
+                            n.checked_sub(4).map(|i| &tokens[i]),
+                            Some(Tokens::Pow | Tokens::Div | Tokens::Mod | Tokens::IntDiv)
+                        );
+                    if fraction {
+                        tokens.insert(n - 3, Tokens::OpenParen);
+                        tokens.push(Tokens::CloseParen);
+                    }
+                    tokens.push(Tokens::UnitMul);
+                }
+                Tokens::Number(_) | Tokens::Ans | Tokens::Variable(_) | Tokens::Not => {
+                    return Err(ParseError::new(pos, ParseErrorKind::ExpectedOperator))
+                }
+                Tokens::Assign => {
+                    if !matches!(tokens.as_slice(), [Tokens::Variable(_)])
+                        && !is_function_head(&tokens)
+                    {
+                        return Err(ParseError::new(pos, ParseErrorKind::InvalidAssignment));
+                    }
+                    expect_operand = true;
+                }
+                Tokens::OpenParen => {
+                    tokens.push(Tokens::Mul);
+                    open_parens.push((pos, false));
+                    expect_operand = true;
+                }
+                Tokens::Function(_) | Tokens::Call(..) => {
+                    tokens.push(Tokens::Mul);
+                    expect_operand = true;
+                }
+                Tokens::Comma => {
+                    if !matches!(open_parens.last(), Some((_, true))) {
+                        return Err(ParseError::new(
+                            pos,
+                            ParseErrorKind::UnexpectedCharacter(','),
+                        ));
+                    }
+                    expect_operand = true;
+                }
+                // postfix operators leave an operand behind
+                Tokens::Factorial | Tokens::Percent => {}
+                Tokens::CloseParen => {
+                    if open_parens.pop().is_none() {
+                        return Err(ParseError::new(pos, ParseErrorKind::UnmatchedCloseParen));
+                    }
+                }
+                Tokens::Div => {
+                    let spaced = |i: usize| chars.get(i).is_none_or(|c| c.is_whitespace());
+                    if pos > 0 && !spaced(pos - 1) && !spaced(pos + 1) {
+                        tight_div = Some(tokens.len());
+                    }
+                    expect_operand = true;
+                }
+                _ => expect_operand = true,
+            }
+        }
+        tokens.push(token);
+    }
+
+    if let Some((pos, _)) = open_parens.pop() {
+        return Err(ParseError::new(pos, ParseErrorKind::UnclosedParen));
+    }
+    if let Some(Tokens::Function(_)) = tokens.last() {
+        return Err(ParseError::new(
+            input.chars().count(),
+            ParseErrorKind::ExpectedCallParen,
+        ));
+    }
+    if expect_operand {
+        return Err(ParseError::new(
+            input.chars().count(),
+            ParseErrorKind::ExpectedOperand,
+        ));
+    }
+
+    Ok(tokens)
+}
+
+/// Whether `tokens` are `f(x, y, ...)` with distinct parameter names.
+fn is_function_head(tokens: &[Tokens]) -> bool {
+    let params = match tokens {
+        [Tokens::Call(..), Tokens::OpenParen, params @ .., Tokens::CloseParen] => params,
+        _ => return false,
+    };
+    let mut names = vec![];
+    for (i, token) in params.iter().enumerate() {
+        match token {
+            Tokens::Variable(name) if i % 2 == 0 && !names.contains(&name) => names.push(name),
+            Tokens::Comma if i % 2 == 1 => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Whether the next character other than whitespace is `=`.
+fn assign_follows(rest: &[char]) -> bool {
+    rest.iter().find(|c| !c.is_whitespace()) == Some(&'=')
+}
+
+/// Whether `name` can be used as a variable: a single identifier that is
+/// not a keyword, function or constant.
+pub fn is_variable_name(name: &str) -> bool {
+    matches!(tokenize(name).as_deref(), Ok([(_, Tokens::Variable(_))]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::Calculator;
+
+    fn evaluate(input: &str) -> Number {
+        Calculator::default()
+            .evaluate(input)
+            .unwrap()
+            .unwrap()
+            .number
+    }
+
+    #[test]
+    fn percent_of() {
+        assert_eq!(
+            parse("50% of 200"),
+            Ok(vec![
+                Tokens::Number(Number::from(50)),
+                Tokens::Percent,
+                Tokens::Mul,
+                Tokens::Number(Number::from(200)),
+            ])
+        );
+        assert_eq!(evaluate("50% of 200"), Number::from(100));
+        assert_eq!(evaluate("10% of 200"), Number::from(20));
+    }
+
+    #[test]
+    fn percent_between_operands_is_modulo() {
+        assert_eq!(
+            parse("7 % 3"),
+            Ok(vec![
+                Tokens::Number(Number::from(7)),
+                Tokens::Mod,
+                Tokens::Number(Number::from(3)),
+            ])
+        );
+        assert_eq!(evaluate("7 % 3"), Number::from(1));
+        assert_eq!(evaluate("7 % -3"), Number::from(1));
+        assert_eq!(evaluate("7 % +3"), Number::from(1));
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        for (input, position, kind) in [
+            ("2 + * 3", 4, ParseErrorKind::ExpectedOperand),
+            ("3 4", 2, ParseErrorKind::ExpectedOperator),
+            ("(1 + 2", 0, ParseErrorKind::UnclosedParen),
+            ("1 + 2)", 5, ParseErrorKind::UnmatchedCloseParen),
+            ("2 $ 3", 2, ParseErrorKind::UnexpectedCharacter('$')),
+            ("sin 2", 4, ParseErrorKind::ExpectedCallParen),
+        ] {
+            assert_eq!(
+                parse(input),
+                Err(ParseError { position, kind }),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            parse("2 + * 3").unwrap_err().to_string(),
+            "expected a number at position 5"
+        );
+    }
+
+    #[test]
+    fn constants_after_an_operand_multiply() {
+        let pi = Tokens::Number(Constant::from_name("pi").unwrap().value());
+        let two = Tokens::Number(Number::from(2));
+        for input in ["2 pi", "2pi", "2*pi"] {
+            assert_eq!(parse(input), Ok(vec![two.clone(), Tokens::Mul, pi.clone()]));
+        }
+        assert_eq!(evaluate("(2)pi"), evaluate("2 pi"));
+        assert_eq!(evaluate("3! e"), evaluate("6 * e"));
+    }
+}
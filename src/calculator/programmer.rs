@@ -0,0 +1,291 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{CalcError, DivisionMode, Tokens};
+
+/// Radix numbers are typed and shown in while in programmer mode.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Base {
+    Bin,
+    Oct,
+    #[default]
+    Dec,
+    Hex,
+}
+
+impl Base {
+    pub const ALL: [Base; 4] = [Base::Bin, Base::Oct, Base::Dec, Base::Hex];
+
+    pub fn radix(self) -> u32 {
+        match self {
+            Base::Bin => 2,
+            Base::Oct => 8,
+            Base::Dec => 10,
+            Base::Hex => 16,
+        }
+    }
+}
+
+impl fmt::Display for Base {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Base::Bin => write!(f, "BIN"),
+            Base::Oct => write!(f, "OCT"),
+            Base::Dec => write!(f, "DEC"),
+            Base::Hex => write!(f, "HEX"),
+        }
+    }
+}
+
+/// Width and signedness of the integer register; results wrap around
+/// like machine arithmetic.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct WordSize {
+    pub bits: u32,
+    pub signed: bool,
+}
+
+impl Default for WordSize {
+    fn default() -> Self {
+        Self {
+            bits: 64,
+            signed: true,
+        }
+    }
+}
+
+impl WordSize {
+    pub const BITS: [u32; 4] = [8, 16, 32, 64];
+
+    fn mask(self) -> u128 {
+        (1u128 << self.bits) - 1
+    }
+
+    /// Keeps the low `bits` bits of `n` and reads them back as a signed
+    /// or unsigned value.
+    pub fn wrap(self, n: i128) -> i128 {
+        let pattern = n as u128 & self.mask();
+        if self.signed && pattern >> (self.bits - 1) == 1 {
+            pattern as i128 - (1i128 << self.bits)
+        } else {
+            pattern as i128
+        }
+    }
+
+    /// Bitwise complement within the word.
+    pub fn not(self, x: i128) -> i128 {
+        self.wrap(!x)
+    }
+
+    /// Integer arithmetic and bitwise operators. `/` truncates toward zero
+    /// as in C, while `mod` and `div` follow `division`; shifts by the word
+    /// size or more clear the word (or fill it with the sign bit for signed
+    /// `shr`).
+    pub fn apply(
+        self,
+        op: &Tokens,
+        x: i128,
+        y: i128,
+        division: DivisionMode,
+    ) -> Result<i128, CalcError> {
+        let result = match op {
+            Tokens::Add => x.wrapping_add(y),
+            Tokens::Sub => x.wrapping_sub(y),
+            Tokens::Mul => x.wrapping_mul(y),
+            Tokens::Div | Tokens::IntDiv | Tokens::Mod if y == 0 => {
+                return Err(CalcError::DivisionByZero)
+            }
+            Tokens::IntDiv if division == DivisionMode::Euclidean => x.wrapping_div_euclid(y),
+            Tokens::Mod if division == DivisionMode::Euclidean => x.wrapping_rem_euclid(y),
+            Tokens::Div | Tokens::IntDiv => x.wrapping_div(y),
+            Tokens::Mod => x.wrapping_rem(y),
+            Tokens::Pow if y < 0 => return Err(CalcError::Domain),
+            Tokens::Pow => x.wrapping_pow(u32::try_from(y).map_err(|_| CalcError::Overflow)?),
+            Tokens::And => x & y,
+            Tokens::Or => x | y,
+            Tokens::Xor => x ^ y,
+            Tokens::Shl | Tokens::Shr | Tokens::Rol | Tokens::Ror if y < 0 => {
+                return Err(CalcError::Domain)
+            }
+            Tokens::Shl if y >= self.bits as i128 => 0,
+            Tokens::Shl => x << y,
+            Tokens::Shr => x >> y.min(127),
+            Tokens::Rol | Tokens::Ror => {
+                let pattern = x as u128 & self.mask();
+                let mut shift = (y % self.bits as i128) as u32;
+                if *op == Tokens::Ror {
+                    shift = (self.bits - shift) % self.bits;
+                }
+                if shift == 0 {
+                    pattern as i128
+                } else {
+                    ((pattern << shift) | (pattern >> (self.bits - shift))) as i128
+                }
+            }
+            _ => return Err(CalcError::MalformedExpression),
+        };
+        Ok(self.wrap(result))
+    }
+}
+
+impl fmt::Display for WordSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.signed { 'i' } else { 'u' };
+        write!(f, "{}{}", sign, self.bits)
+    }
+}
+
+impl FromStr for WordSize {
+    type Err = ();
+
+    /// Reads names like `i32` or `u8`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let signed = match name.get(..1) {
+            Some("i") => true,
+            Some("u") => false,
+            _ => return Err(()),
+        };
+        let bits = name[1..].parse().map_err(|_| ())?;
+        if WordSize::BITS.contains(&bits) {
+            Ok(WordSize { bits, signed })
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Settings of programmer mode, where every value is a wrapped integer.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct ProgrammerMode {
+    pub base: Base,
+    pub word: WordSize,
+}
+
+impl ProgrammerMode {
+    /// Reads digits typed in the current base, `None` if they do not fit
+    /// the word. Outside decimal the digits are a bit pattern, so `FF` is
+    /// -1 for a signed byte.
+    pub fn parse(self, input: &str) -> Option<i128> {
+        let value = i128::from_str_radix(input, self.base.radix()).ok()?;
+        let (min, max) = if self.base != Base::Dec || !self.word.signed {
+            (0, self.word.mask() as i128)
+        } else {
+            (
+                -(1i128 << (self.word.bits - 1)),
+                (1i128 << (self.word.bits - 1)) - 1,
+            )
+        };
+        if value < min || value > max {
+            None
+        } else {
+            Some(self.word.wrap(value))
+        }
+    }
+
+    /// Renders a wrapped value; other bases than decimal show the two's
+    /// complement bit pattern.
+    pub fn format(self, n: i128) -> String {
+        let pattern = n as u128 & self.word.mask();
+        match self.base {
+            Base::Bin => format!("{:b}", pattern),
+            Base::Oct => format!("{:o}", pattern),
+            Base::Dec => self.word.wrap(n).to_string(),
+            Base::Hex => format!("{:X}", pattern),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const I8: WordSize = WordSize {
+        bits: 8,
+        signed: true,
+    };
+    const U8: WordSize = WordSize {
+        bits: 8,
+        signed: false,
+    };
+
+    fn apply(word: WordSize, op: Tokens, x: i128, y: i128) -> Result<i128, CalcError> {
+        word.apply(&op, x, y, DivisionMode::default())
+    }
+
+    #[test]
+    fn wrap() {
+        assert_eq!(I8.wrap(127), 127);
+        assert_eq!(I8.wrap(128), -128);
+        assert_eq!(I8.wrap(255), -1);
+        assert_eq!(I8.wrap(-129), 127);
+        assert_eq!(U8.wrap(256), 0);
+        assert_eq!(U8.wrap(-1), 255);
+        assert_eq!(WordSize::default().wrap(1 << 63), i64::MIN as i128);
+        assert_eq!(I8.not(0), -1);
+        assert_eq!(U8.not(0), 255);
+    }
+
+    #[test]
+    fn parse_and_format() {
+        let hex = |word| ProgrammerMode {
+            base: Base::Hex,
+            word,
+        };
+        let dec = |word| ProgrammerMode {
+            base: Base::Dec,
+            word,
+        };
+        assert_eq!(hex(I8).parse("FF"), Some(-1));
+        assert_eq!(hex(U8).parse("FF"), Some(255));
+        assert_eq!(hex(I8).parse("100"), None);
+        assert_eq!(dec(I8).parse("-128"), Some(-128));
+        assert_eq!(dec(I8).parse("128"), None);
+        assert_eq!(dec(U8).parse("255"), Some(255));
+        assert_eq!(dec(U8).parse("-1"), None);
+
+        assert_eq!(hex(I8).format(-1), "FF");
+        assert_eq!(dec(I8).format(-1), "-1");
+        assert_eq!(dec(U8).format(-1), "255");
+        let bin = ProgrammerMode {
+            base: Base::Bin,
+            word: I8,
+        };
+        assert_eq!(bin.format(-128), "10000000");
+    }
+
+    #[test]
+    fn shifts_at_the_word_boundary() {
+        assert_eq!(apply(U8, Tokens::Shl, 1, 7), Ok(128));
+        assert_eq!(apply(U8, Tokens::Shl, 1, 8), Ok(0));
+        assert_eq!(apply(I8, Tokens::Shl, 1, 7), Ok(-128));
+        assert_eq!(apply(I8, Tokens::Shr, -128, 7), Ok(-1));
+        assert_eq!(apply(I8, Tokens::Shr, -128, 8), Ok(-1));
+        assert_eq!(apply(U8, Tokens::Shr, 128, 7), Ok(1));
+        assert_eq!(apply(U8, Tokens::Shr, 128, 8), Ok(0));
+        assert_eq!(apply(U8, Tokens::Shl, 1, -1), Err(CalcError::Domain));
+    }
+
+    #[test]
+    fn rotations_at_the_word_boundary() {
+        assert_eq!(apply(U8, Tokens::Rol, 0x81, 1), Ok(0x03));
+        assert_eq!(apply(U8, Tokens::Ror, 0x81, 1), Ok(0xC0));
+        assert_eq!(apply(U8, Tokens::Rol, 0x81, 8), Ok(0x81));
+        assert_eq!(apply(U8, Tokens::Ror, 0x81, 9), Ok(0xC0));
+        assert_eq!(apply(I8, Tokens::Rol, -128, 1), Ok(1));
+        assert_eq!(apply(I8, Tokens::Ror, 1, 1), Ok(-128));
+        let word = WordSize::default();
+        assert_eq!(apply(word, Tokens::Rol, i64::MIN as i128, 1), Ok(1));
+    }
+
+    #[test]
+    fn division_follows_the_division_mode() {
+        let truncated = |op, x, y| I8.apply(&op, x, y, DivisionMode::Truncated);
+        assert_eq!(apply(I8, Tokens::Mod, -7, 2), Ok(1));
+        assert_eq!(apply(I8, Tokens::IntDiv, -7, 2), Ok(-4));
+        assert_eq!(apply(I8, Tokens::Div, -7, 2), Ok(-3));
+        assert_eq!(truncated(Tokens::Mod, -7, 2), Ok(-1));
+        assert_eq!(truncated(Tokens::IntDiv, -7, 2), Ok(-3));
+        assert_eq!(apply(I8, Tokens::Mod, 7, 0), Err(CalcError::DivisionByZero));
+        assert_eq!(apply(I8, Tokens::IntDiv, -128, -1), Ok(-128));
+    }
+}
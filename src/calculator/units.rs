@@ -0,0 +1,321 @@
+use std::fmt;
+
+use super::{CalcError, Number};
+
+/// Powers of the base dimensions: metre, kilogram, second, ampere,
+/// kelvin, mole, bit and money.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Dimension([i8; 8]);
+
+impl Dimension {
+    pub fn is_none(self) -> bool {
+        self == Dimension::default()
+    }
+
+    /// `self + rhs * power`, `None` when a power leaves the `i8` range.
+    fn add(self, rhs: Dimension, power: i32) -> Option<Dimension> {
+        let power = i8::try_from(power).ok()?;
+        let mut sum = self.0;
+        for (x, y) in sum.iter_mut().zip(rhs.0) {
+            *x = x.checked_add(y.checked_mul(power)?)?;
+        }
+        Some(Dimension(sum))
+    }
+}
+
+const NONE: Dimension = Dimension([0, 0, 0, 0, 0, 0, 0, 0]);
+const LENGTH: Dimension = Dimension([1, 0, 0, 0, 0, 0, 0, 0]);
+const AREA: Dimension = Dimension([2, 0, 0, 0, 0, 0, 0, 0]);
+const VOLUME: Dimension = Dimension([3, 0, 0, 0, 0, 0, 0, 0]);
+const MASS: Dimension = Dimension([0, 1, 0, 0, 0, 0, 0, 0]);
+const TIME: Dimension = Dimension([0, 0, 1, 0, 0, 0, 0, 0]);
+const FREQUENCY: Dimension = Dimension([0, 0, -1, 0, 0, 0, 0, 0]);
+const SPEED: Dimension = Dimension([1, 0, -1, 0, 0, 0, 0, 0]);
+const FORCE: Dimension = Dimension([1, 1, -2, 0, 0, 0, 0, 0]);
+const ENERGY: Dimension = Dimension([2, 1, -2, 0, 0, 0, 0, 0]);
+const POWER: Dimension = Dimension([2, 1, -3, 0, 0, 0, 0, 0]);
+const PRESSURE: Dimension = Dimension([-1, 1, -2, 0, 0, 0, 0, 0]);
+const CURRENT: Dimension = Dimension([0, 0, 0, 1, 0, 0, 0, 0]);
+const CHARGE: Dimension = Dimension([0, 0, 1, 1, 0, 0, 0, 0]);
+const VOLTAGE: Dimension = Dimension([2, 1, -3, -1, 0, 0, 0, 0]);
+const RESISTANCE: Dimension = Dimension([2, 1, -3, -2, 0, 0, 0, 0]);
+const TEMPERATURE: Dimension = Dimension([0, 0, 0, 0, 1, 0, 0, 0]);
+const AMOUNT: Dimension = Dimension([0, 0, 0, 0, 0, 1, 0, 0]);
+const DATA: Dimension = Dimension([0, 0, 0, 0, 0, 0, 1, 0]);
+const MONEY: Dimension = Dimension([0, 0, 0, 0, 0, 0, 0, 1]);
+
+/// Unit names, their size in SI base units (written exactly, as a decimal
+/// or a fraction) and their dimension.
+const UNITS: [(&str, &str, Dimension); 72] = [
+    ("m", "1", LENGTH),
+    ("km", "1000", LENGTH),
+    ("cm", "0.01", LENGTH),
+    ("mm", "0.001", LENGTH),
+    ("um", "1e-6", LENGTH),
+    ("nm", "1e-9", LENGTH),
+    ("mi", "1609.344", LENGTH),
+    ("yd", "0.9144", LENGTH),
+    ("ft", "0.3048", LENGTH),
+    ("inch", "0.0254", LENGTH),
+    ("nmi", "1852", LENGTH),
+    ("ha", "10000", AREA),
+    ("acre", "4046.8564224", AREA),
+    ("L", "0.001", VOLUME),
+    ("mL", "1e-6", VOLUME),
+    ("gal", "0.003785411784", VOLUME),
+    ("kg", "1", MASS),
+    ("g", "0.001", MASS),
+    ("mg", "1e-6", MASS),
+    ("t", "1000", MASS),
+    ("lb", "0.45359237", MASS),
+    ("oz", "0.028349523125", MASS),
+    ("s", "1", TIME),
+    ("ms", "0.001", TIME),
+    ("us", "1e-6", TIME),
+    ("ns", "1e-9", TIME),
+    ("min", "60", TIME),
+    ("h", "3600", TIME),
+    ("day", "86400", TIME),
+    ("week", "604800", TIME),
+    ("yr", "31557600", TIME),
+    ("Hz", "1", FREQUENCY),
+    ("kHz", "1e3", FREQUENCY),
+    ("MHz", "1e6", FREQUENCY),
+    ("GHz", "1e9", FREQUENCY),
+    ("mph", "0.44704", SPEED),
+    ("kn", "1852/3600", SPEED),
+    ("N", "1", FORCE),
+    ("lbf", "4.4482216152605", FORCE),
+    ("J", "1", ENERGY),
+    ("kJ", "1e3", ENERGY),
+    ("cal", "4.184", ENERGY),
+    ("kcal", "4184", ENERGY),
+    ("Wh", "3600", ENERGY),
+    ("kWh", "3.6e6", ENERGY),
+    ("eV", "1.602176634e-19", ENERGY),
+    ("W", "1", POWER),
+    ("kW", "1e3", POWER),
+    ("MW", "1e6", POWER),
+    ("hp", "745.69987158227022", POWER),
+    ("Pa", "1", PRESSURE),
+    ("kPa", "1e3", PRESSURE),
+    ("bar", "1e5", PRESSURE),
+    ("psi", "44482216152605/6451600000", PRESSURE),
+    ("atm", "101325", PRESSURE),
+    ("A", "1", CURRENT),
+    ("mA", "0.001", CURRENT),
+    ("C", "1", CHARGE),
+    ("V", "1", VOLTAGE),
+    ("ohm", "1", RESISTANCE),
+    ("K", "1", TEMPERATURE),
+    ("mol", "1", AMOUNT),
+    ("bit", "1", DATA),
+    ("B", "8", DATA),
+    ("kB", "8e3", DATA),
+    ("MB", "8e6", DATA),
+    ("GB", "8e9", DATA),
+    ("TB", "8e12", DATA),
+    ("KiB", "8192", DATA),
+    ("MiB", "8388608", DATA),
+    ("GiB", "8589934592", DATA),
+    ("TiB", "8796093022208", DATA),
+];
+
+/// A named unit of measurement.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Unit {
+    pub name: String,
+    /// Size in SI base units.
+    scale: Number,
+    dimension: Dimension,
+}
+
+impl Unit {
+    pub fn from_name(name: &str) -> Option<Unit> {
+        let (_, scale, dimension) = UNITS.iter().find(|(unit, ..)| *unit == name)?;
+        let scale = match scale.split_once('/') {
+            Some((numer, denom)) => Number::parse(numer)?.div(&Number::parse(denom)?).ok()?,
+            None => Number::parse(scale)?,
+        };
+        Some(Unit {
+            name: name.to_string(),
+            scale,
+            dimension: *dimension,
+        })
+    }
+
+    /// A currency worth `value` units of the rate table's base currency.
+    pub fn currency(code: &str, value: Number) -> Unit {
+        Unit {
+            name: code.to_string(),
+            scale: value,
+            dimension: MONEY,
+        }
+    }
+}
+
+/// Product of units with whole powers, such as `km/h` or `ft²`; empty for
+/// plain numbers.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Units(Vec<(Unit, i32)>);
+
+impl Units {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.checked_dimension()
+            .expect("units are checked to fit a dimension when built")
+    }
+
+    fn checked_dimension(&self) -> Option<Dimension> {
+        self.0
+            .iter()
+            .try_fold(NONE, |dim, (unit, power)| dim.add(unit.dimension, *power))
+    }
+
+    /// Size of one of these units in SI base units.
+    pub fn scale(&self) -> Result<Number, CalcError> {
+        let mut scale = Number::from(1);
+        for (unit, power) in &self.0 {
+            scale = scale.mul(&unit.scale.pow(&Number::from(*power as i128))?)?;
+        }
+        Ok(scale)
+    }
+
+    /// Units of a product. Powers of the same unit are merged, and units
+    /// whose dimensions cancel out leave a plain number. Powers too large
+    /// for a dimension are an overflow.
+    pub fn mul(&self, rhs: &Units) -> Result<Units, CalcError> {
+        let mut factors = self.0.clone();
+        for (unit, power) in &rhs.0 {
+            match factors.iter_mut().find(|(u, _)| u.name == unit.name) {
+                Some((_, p)) => *p = p.checked_add(*power).ok_or(CalcError::Overflow)?,
+                None => factors.push((unit.clone(), *power)),
+            }
+        }
+        factors.retain(|(_, power)| *power != 0);
+        let units = Units(factors);
+        match units.checked_dimension() {
+            Some(dimension) if dimension.is_none() => Ok(Units::default()),
+            Some(_) => Ok(units),
+            None => Err(CalcError::Overflow),
+        }
+    }
+
+    pub fn div(&self, rhs: &Units) -> Result<Units, CalcError> {
+        self.mul(&rhs.powi(-1)?)
+    }
+
+    pub fn powi(&self, n: i32) -> Result<Units, CalcError> {
+        let factors = self
+            .0
+            .iter()
+            .map(|(u, p)| p.checked_mul(n).map(|p| (u.clone(), p)))
+            .collect::<Option<_>>()
+            .ok_or(CalcError::Overflow)?;
+        Units::default().mul(&Units(factors))
+    }
+
+    /// Fails unless both sides measure the same kind of thing.
+    pub fn check_compatible(&self, rhs: &Units) -> Result<(), CalcError> {
+        if self.dimension() == rhs.dimension() {
+            Ok(())
+        } else {
+            let name = |u: &Units| {
+                if u.is_empty() {
+                    "a plain number".to_string()
+                } else {
+                    u.to_string()
+                }
+            };
+            Err(CalcError::IncompatibleUnits(name(self), name(rhs)))
+        }
+    }
+}
+
+impl From<Unit> for Units {
+    fn from(unit: Unit) -> Self {
+        Units(vec![(unit, 1)])
+    }
+}
+
+/// Writes `unit` raised to `power`, e.g. "m²" or "s^4".
+fn write_power(f: &mut fmt::Formatter<'_>, unit: &Unit, power: i32) -> fmt::Result {
+    match power {
+        1 => write!(f, "{}", unit.name),
+        2 => write!(f, "{}²", unit.name),
+        3 => write!(f, "{}³", unit.name),
+        _ => write!(f, "{}^{}", unit.name, power),
+    }
+}
+
+impl fmt::Display for Units {
+    /// Numerator units first, the others after a slash: "kg m/s²",
+    /// "J/(mol K)".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (above, below): (Vec<_>, Vec<_>) = self.0.iter().partition(|(_, p)| *p > 0);
+        if above.is_empty() {
+            write!(f, "1")?;
+        }
+        for (i, (unit, power)) in above.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write_power(f, unit, *power)?;
+        }
+        if below.is_empty() {
+            return Ok(());
+        }
+        write!(f, "/")?;
+        if below.len() > 1 {
+            write!(f, "(")?;
+        }
+        for (i, (unit, power)) in below.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write_power(f, unit, -power)?;
+        }
+        if below.len() > 1 {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// A number with the units it is measured in. The number is kept in SI
+/// base units so that arithmetic needs no conversions.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Quantity {
+    pub number: Number,
+    pub units: Units,
+}
+
+impl Quantity {
+    /// One of `unit`.
+    pub fn of(unit: Unit) -> Quantity {
+        Quantity {
+            number: unit.scale.clone(),
+            units: Units::from(unit),
+        }
+    }
+
+    /// The number of units, e.g. 5 for `5 km`.
+    pub fn magnitude(&self) -> Result<Number, CalcError> {
+        if self.units.is_empty() {
+            return Ok(self.number.clone());
+        }
+        self.number.div(&self.units.scale()?)
+    }
+}
+
+impl From<Number> for Quantity {
+    fn from(number: Number) -> Self {
+        Quantity {
+            number,
+            units: Units::default(),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use crate::calculator::{
+    AngleMode, Base, CalcError, Calculator, ComplexDisplay, DivisionMode, FractionDisplay,
+    Precision, ProgrammerMode, RateTable, WordSize,
+};
+
+const PROMPT: &str = "> ";
+
+const USAGE: &str = "\
+usage: calculator-rs                  start the desktop calculator
+       calculator-rs repl             evaluate expressions interactively
+       calculator-rs -e EXPR          print the value of EXPR
+       calculator-rs --batch [FILE]   evaluate FILE (or stdin) line by line
+       calc [COMMAND]                 the same commands, `repl` when none is given
+
+options (before the command):
+       --division truncated|euclidean   sign rules of `mod` and `div`
+       --angle deg|rad|grad             angle unit of trigonometric functions
+       --precision N                    decimal places of quotients that never end
+       --fractions improper|mixed|decimal  keep quotients exact, shown in this form
+       --complex rect|polar             form of complex results
+       --base bin|oct|dec|hex           programmer mode, results in this base
+       --word i8|u8|i16|..|u64          programmer mode with this word size
+       --rates FILE                     exchange rates for currency codes (TOML or JSON)";
+
+/// Runs the command line front-end for the given arguments (program name
+/// excluded).
+pub fn run(args: &[String]) -> ExitCode {
+    let (calculator, args) = match configure(args) {
+        Ok(configured) => configured,
+        Err(message) => {
+            eprintln!("error: {}\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match args.first().map(String::as_str) {
+        None => repl(calculator),
+        Some("repl") if args.len() == 1 => repl(calculator),
+        Some("-e") if args.len() == 2 => eval(calculator, &args[1]),
+        Some("--batch") if args.len() == 1 => batch(calculator, io::stdin().lock()),
+        Some("--batch") if args.len() == 2 && args[1] == "-" => {
+            batch(calculator, io::stdin().lock())
+        }
+        Some("--batch") if args.len() == 2 => match File::open(&args[1]) {
+            Ok(file) => batch(calculator, BufReader::new(file)),
+            Err(err) => {
+                eprintln!("error: {}: {}", args[1], err);
+                ExitCode::FAILURE
+            }
+        },
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Applies the leading `--option value` pairs to a fresh calculator and
+/// returns it with the remaining arguments.
+fn configure(mut args: &[String]) -> Result<(Calculator, &[String]), String> {
+    let mut calculator = Calculator::default();
+
+    while let [option, value, rest @ ..] = args {
+        match (option.as_str(), value.as_str()) {
+            ("--division", "truncated") => calculator.set_division_mode(DivisionMode::Truncated),
+            ("--division", "euclidean") => calculator.set_division_mode(DivisionMode::Euclidean),
+            ("--division", _) => return Err(format!("unknown division mode '{}'", value)),
+            ("--angle", "deg") => calculator.set_angle_mode(AngleMode::Degrees),
+            ("--angle", "rad") => calculator.set_angle_mode(AngleMode::Radians),
+            ("--angle", "grad") => calculator.set_angle_mode(AngleMode::Gradians),
+            ("--angle", _) => return Err(format!("unknown angle mode '{}'", value)),
+            ("--complex", "rect") => calculator.set_complex_display(ComplexDisplay::Rectangular),
+            ("--complex", "polar") => calculator.set_complex_display(ComplexDisplay::Polar),
+            ("--complex", _) => return Err(format!("unknown complex form '{}'", value)),
+            ("--precision", _) => {
+                let places = value
+                    .parse()
+                    .map_err(|_| format!("invalid precision '{}'", value))?;
+                calculator.set_precision(Precision(places));
+            }
+            ("--fractions", _) => {
+                let form = FractionDisplay::ALL
+                    .into_iter()
+                    .find(|f| f.to_string() == *value)
+                    .ok_or_else(|| format!("unknown fraction form '{}'", value))?;
+                calculator.set_fractions(Some(form));
+            }
+            ("--base", _) => {
+                let base = Base::ALL
+                    .into_iter()
+                    .find(|b| b.to_string().eq_ignore_ascii_case(value))
+                    .ok_or_else(|| format!("unknown base '{}'", value))?;
+                let mode = calculator.programmer().unwrap_or_default();
+                calculator.set_programmer(Some(ProgrammerMode { base, ..mode }));
+            }
+            ("--rates", _) => calculator.set_rates(Some(RateTable::load(Path::new(value))?)),
+            ("--word", _) => {
+                let word: WordSize = value
+                    .parse()
+                    .map_err(|_| format!("unknown word size '{}'", value))?;
+                let mode = calculator.programmer().unwrap_or_default();
+                calculator.set_programmer(Some(ProgrammerMode { word, ..mode }));
+            }
+            _ => break,
+        }
+        args = rest;
+    }
+
+    Ok((calculator, args))
+}
+
+/// Read-eval-print loop over stdin; `ans` refers to the previous result.
+fn repl(mut calculator: Calculator) -> ExitCode {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(rates) = calculator.rates() {
+        println!("exchange rates of {} in {}", rates.date, rates.base);
+    }
+
+    loop {
+        match editor.readline(PROMPT) {
+            Ok(line) => {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let _ = editor.add_history_entry(line.trim());
+                // the untrimmed line, so that error positions match the echo
+                match calculator.evaluate(&line) {
+                    Ok(Some(result)) => println!("{}", calculator.format_result(&result)),
+                    Ok(None) => {}
+                    Err(err) => report(&err, PROMPT.len()),
+                }
+            }
+            // Ctrl-C drops the current line only
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+}
+
+/// Evaluates a single expression given on the command line.
+fn eval(mut calculator: Calculator, expression: &str) -> ExitCode {
+    match calculator.evaluate(expression) {
+        Ok(result) => {
+            if let Some(result) = result {
+                println!("{}", calculator.format_result(&result));
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Evaluates one expression per line, printing `line: result`. Blank lines
+/// and `#` comments are skipped; evaluation goes on past failing lines but
+/// the exit code reports them.
+fn batch(mut calculator: Calculator, input: impl BufRead) -> ExitCode {
+    let mut failed = false;
+
+    for (index, line) in input.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        let expression = line.trim();
+        if expression.is_empty() || expression.starts_with('#') {
+            continue;
+        }
+        match calculator.evaluate(expression) {
+            Ok(Some(result)) => println!("{}: {}", index + 1, calculator.format_result(&result)),
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("{}: error: {}", index + 1, err);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Prints an error, pointing at the offending column of parse errors that
+/// were echoed `indent` characters from the left edge.
+fn report(err: &CalcError, indent: usize) {
+    if let CalcError::Parse(parse) = err {
+        eprintln!("{}^", " ".repeat(indent + parse.position));
+    }
+    eprintln!("error: {}", err);
+}
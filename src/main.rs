@@ -1,25 +1,519 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+use std::process::ExitCode;
+use std::time::Duration;
+
 use eframe::egui;
 use egui::{FontFamily, FontId, TextStyle};
 
-mod calculator;
+use calculator_rs::{calculator, cli};
 
-use calculator::{Calculator, Events};
+use calculator::{
+    is_variable_name, AngleMode, Base, Calculator, ComplexDisplay, Constant, Events,
+    FractionDisplay, Function, Number, ProgrammerMode, RateTable, WordSize, CONSTANTS,
+    MEMORY_SLOTS,
+};
 
-fn main() {
+fn main() -> ExitCode {
     // Log to stdout (if you run with `RUST_LOG=debug`).
     tracing_subscriber::fmt::init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return cli::run(&args);
+    }
+
     let options = eframe::NativeOptions {
-        initial_window_size: Some(egui::vec2(250.0, 380.0)),
+        initial_window_size: Some(Mode::Standard.window_size()),
         ..Default::default()
     };
-    let _ = eframe::run_native(
+    eframe::run_native(
         "Calculator",
         options,
-        Box::new(|_cc| Box::new(Calculator::default())),
+        Box::new(|_cc| Box::<CalculatorApp>::default()),
     );
+    ExitCode::SUCCESS
+}
+
+/// How long a button stays highlighted after its keyboard key is pressed.
+const FLASH_SECONDS: f64 = 0.15;
+
+/// Function keys of the scientific panel, row by row.
+const FUNCTION_ROWS: [[Function; 3]; 5] = [
+    [Function::Sin, Function::Cos, Function::Tan],
+    [Function::Asin, Function::Acos, Function::Atan],
+    [Function::Sinh, Function::Cosh, Function::Tanh],
+    [Function::Ln, Function::Log10, Function::Log2],
+    [Function::Exp, Function::Cbrt, Function::Abs],
+];
+
+/// Combinatorics and number theory keys; two-argument functions take the
+/// typed number first, as in `5 nCr 2`.
+const INTEGER_ROWS: [[Function; 4]; 2] = [
+    [Function::NCr, Function::NPr, Function::Gcd, Function::Lcm],
+    [
+        Function::IsPrime,
+        Function::Factor,
+        Function::Fib,
+        Function::ModPow,
+    ],
+];
+
+/// Labels of the hexadecimal digits above 9.
+const HEX_DIGITS: [&str; 6] = ["A", "B", "C", "D", "E", "F"];
+
+/// Keypad layouts; each one has its own window size.
+#[derive(Default, PartialEq, Clone, Copy)]
+enum Mode {
+    #[default]
+    Standard,
+    Scientific,
+    Programmer,
+}
+
+impl Mode {
+    fn window_size(self) -> egui::Vec2 {
+        match self {
+            Mode::Standard => egui::vec2(250.0, 440.0),
+            Mode::Scientific | Mode::Programmer => egui::vec2(430.0, 440.0),
+        }
+    }
+
+    fn next(self) -> Mode {
+        match self {
+            Mode::Standard => Mode::Scientific,
+            Mode::Scientific => Mode::Programmer,
+            Mode::Programmer => Mode::Standard,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Mode::Standard => "Std",
+            Mode::Scientific => "Sci",
+            Mode::Programmer => "Prog",
+        }
+    }
+}
+
+#[derive(Default)]
+struct CalculatorApp {
+    calculator: Calculator,
+    /// Event of the button last triggered from the keyboard, and when.
+    flash: Option<(Events, f64)>,
+    show_history: bool,
+    show_variables: bool,
+    /// Name typed for storing the shown number as a variable.
+    variable_name: String,
+    /// Definition or assignment typed into the variables panel, and why
+    /// it was rejected.
+    definition: String,
+    definition_error: Option<String>,
+    /// Path of the exchange rate file to load, and why loading it failed.
+    rates_path: String,
+    rates_error: Option<String>,
+    /// Memory register the M keys work on.
+    memory_slot: usize,
+    mode: Mode,
+    /// Base and word size, kept while another keypad is shown.
+    programmer: ProgrammerMode,
+}
+
+/// Maps typed text (main row or numpad) to the event of its button. The
+/// letters A to F are digits only while typing hexadecimal.
+fn text_key(text: &str, hex: bool) -> Option<Events> {
+    let event = match text {
+        "+" => Events::Add,
+        "-" => Events::Sub,
+        "*" => Events::Mul,
+        "/" => Events::Div,
+        "^" => Events::Pow,
+        "%" => Events::Percent,
+        "!" => Events::Factorial,
+        "." => Events::Decimal,
+        "," => Events::Comma,
+        "(" => Events::OpenParen,
+        ")" => Events::CloseParen,
+        "=" => Events::Eq,
+        "&" => Events::And,
+        "|" => Events::Or,
+        "~" => Events::Not,
+        _ => {
+            if let Some(digit) = HEX_DIGITS.iter().position(|d| d.eq_ignore_ascii_case(text)) {
+                return hex.then_some(Events::Number(10 + digit as i64));
+            }
+            let digit = text.parse().ok().filter(|d| (0..10).contains(d))?;
+            Events::Number(digit)
+        }
+    };
+    Some(event)
+}
+
+/// Maps non-text keys to the event of their button.
+fn named_key(key: egui::Key) -> Option<Events> {
+    match key {
+        egui::Key::Enter => Some(Events::Eq),
+        egui::Key::Backspace => Some(Events::Backspace),
+        egui::Key::Escape => Some(Events::Reset),
+        _ => None,
+    }
+}
+
+impl CalculatorApp {
+    /// Dispatches keyboard input and remembers which button to flash.
+    fn handle_keyboard(&mut self, ctx: &egui::Context) {
+        // typing into a text field is not meant for the keypad
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (events, time) = {
+            let input = ctx.input();
+            (input.events.clone(), input.time)
+        };
+        let hex = self.calculator.programmer().map(|p| p.base) == Some(Base::Hex);
+
+        for event in events {
+            let key = match event {
+                egui::Event::Text(text) => text_key(&text, hex),
+                egui::Event::Key {
+                    key, pressed: true, ..
+                } => named_key(key),
+                _ => None,
+            };
+            if let Some(event) = key {
+                self.calculator.dispatch(event.clone());
+                self.flash = Some((event, time));
+            }
+        }
+    }
+
+    /// A calculator key that dispatches `event` when clicked and lights up
+    /// while its keyboard shortcut was just used.
+    fn key(&mut self, ui: &mut egui::Ui, label: &str, event: Events) {
+        let mut button = egui::Button::new(label);
+        if let Some((flashed, since)) = &self.flash {
+            let elapsed = ui.input().time - since;
+            if *flashed == event && elapsed < FLASH_SECONDS {
+                button = button.fill(ui.visuals().selection.bg_fill);
+                ui.ctx()
+                    .request_repaint_after(Duration::from_secs_f64(FLASH_SECONDS - elapsed));
+            }
+        }
+        if ui.add(button).clicked() {
+            self.calculator.dispatch(event);
+        }
+    }
+
+    /// Function keys, constants, complex numbers, the angle mode and
+    /// fraction switches.
+    fn scientific_panel(&mut self, ctx: &egui::Context) {
+        let shown = self.mode == Mode::Scientific;
+        egui::SidePanel::left("scientific").show_animated(ctx, shown, |ui| {
+            for row in FUNCTION_ROWS {
+                ui.horizontal(|ui| {
+                    for func in row {
+                        self.key(ui, func.name(), Events::Function(func));
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                if ui.button("x²").clicked() {
+                    self.calculator.dispatch(Events::Pow);
+                    self.calculator.dispatch(Events::Value(Number::from(2)));
+                }
+                self.key(ui, "√", Events::Function(Function::Sqrt));
+                self.key(ui, "n!", Events::Factorial);
+            });
+            ui.horizontal(|ui| {
+                self.key(ui, "mod", Events::Mod);
+                self.key(ui, "div", Events::IntDiv);
+                self.key(ui, ",", Events::Comma);
+            });
+            for row in INTEGER_ROWS {
+                ui.horizontal(|ui| {
+                    for func in row {
+                        self.key(ui, func.name(), Events::Function(func));
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                self.key(ui, "i", Events::Value(Number::I));
+                for func in [Function::Re, Function::Im, Function::Conj, Function::Arg] {
+                    self.key(ui, func.name(), Events::Function(func));
+                }
+                let form = self.calculator.complex_display();
+                if ui
+                    .button(form.to_string())
+                    .on_hover_text("complex form")
+                    .clicked()
+                {
+                    self.calculator.set_complex_display(match form {
+                        ComplexDisplay::Rectangular => ComplexDisplay::Polar,
+                        ComplexDisplay::Polar => ComplexDisplay::Rectangular,
+                    });
+                }
+            });
+            ui.horizontal(|ui| {
+                let mut exact = self.calculator.fractions().is_some();
+                if ui
+                    .checkbox(&mut exact, "a/b")
+                    .on_hover_text("exact fractions")
+                    .changed()
+                {
+                    self.calculator
+                        .set_fractions(exact.then(FractionDisplay::default));
+                }
+                let form = self.calculator.fractions().unwrap_or_default();
+                ui.add_enabled_ui(exact, |ui| {
+                    self.key(ui, &form.to_string(), Events::CycleFraction)
+                });
+            });
+            ui.horizontal(|ui| {
+                for name in ["pi", "e"] {
+                    let constant = Constant::from_name(name).unwrap();
+                    self.key(ui, name, Events::Value(constant.value()));
+                }
+                let mut picked = None;
+                egui::ComboBox::from_id_source("constants")
+                    .selected_text("const")
+                    .show_ui(ui, |ui| {
+                        for constant in &CONSTANTS {
+                            let label = format!("{}  {}", constant.name, constant.description);
+                            let value = format!("{} {}", constant.value(), constant.unit);
+                            if ui
+                                .selectable_label(false, label)
+                                .on_hover_text(value.trim_end())
+                                .clicked()
+                            {
+                                picked = Some(constant.value());
+                            }
+                        }
+                    });
+                if let Some(value) = picked {
+                    self.calculator.dispatch(Events::Value(value));
+                }
+                let angle = self.calculator.angle_mode();
+                if ui
+                    .button(angle.to_string())
+                    .on_hover_text("angle unit")
+                    .clicked()
+                {
+                    self.calculator.set_angle_mode(match angle {
+                        AngleMode::Degrees => AngleMode::Radians,
+                        AngleMode::Radians => AngleMode::Gradians,
+                        AngleMode::Gradians => AngleMode::Degrees,
+                    });
+                }
+            });
+        });
+    }
+
+    /// Base and word size selection, hex digits and bitwise operators.
+    fn programmer_panel(&mut self, ctx: &egui::Context) {
+        let shown = self.mode == Mode::Programmer;
+        egui::SidePanel::left("programmer").show_animated(ctx, shown, |ui| {
+            let mut mode = self.programmer;
+            ui.horizontal(|ui| {
+                for base in Base::ALL {
+                    ui.selectable_value(&mut mode.base, base, base.to_string());
+                }
+            });
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("word")
+                    .selected_text(format!("{} bit", mode.word.bits))
+                    .show_ui(ui, |ui| {
+                        for bits in WordSize::BITS {
+                            ui.selectable_value(&mut mode.word.bits, bits, format!("{} bit", bits));
+                        }
+                    });
+                ui.checkbox(&mut mode.word.signed, "signed");
+            });
+            if mode != self.programmer {
+                self.programmer = mode;
+                self.calculator.set_programmer(Some(mode));
+            }
+
+            for row in HEX_DIGITS.chunks(3) {
+                ui.horizontal(|ui| {
+                    for label in row {
+                        let digit = 10 + HEX_DIGITS.iter().position(|d| d == label).unwrap() as i64;
+                        let enabled = self.calculator.accepts_digit(digit);
+                        ui.add_enabled_ui(enabled, |ui| self.key(ui, label, Events::Number(digit)));
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                self.key(ui, "and", Events::And);
+                self.key(ui, "or", Events::Or);
+                self.key(ui, "xor", Events::Xor);
+                self.key(ui, "not", Events::Not);
+            });
+            ui.horizontal(|ui| {
+                self.key(ui, "shl", Events::Shl);
+                self.key(ui, "shr", Events::Shr);
+                self.key(ui, "rol", Events::Rol);
+                self.key(ui, "ror", Events::Ror);
+            });
+            ui.horizontal(|ui| {
+                self.key(ui, "mod", Events::Mod);
+                self.key(ui, "div", Events::IntDiv);
+            });
+        });
+    }
+
+    /// A digit key, greyed out when the current base has no such digit.
+    fn digit_key(&mut self, ui: &mut egui::Ui, digit: i64) {
+        let enabled = self.calculator.accepts_digit(digit);
+        ui.add_enabled_ui(enabled, |ui| {
+            self.key(ui, &digit.to_string(), Events::Number(digit))
+        });
+    }
+
+    /// Assigned variables and user functions; clicking a variable enters
+    /// its value. The shown number can be stored under a new name, and
+    /// lines like `f(x) = x^2` or `rate = 0.07` can be typed in.
+    fn variables_panel(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::right("variables").show_animated(ctx, self.show_variables, |ui| {
+            ui.label("Variables");
+            ui.separator();
+
+            let mut event = None;
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (name, value) in self.calculator.variables() {
+                    ui.horizontal(|ui| {
+                        let text = format!("{} = {}", name, self.calculator.format_quantity(value));
+                        if ui
+                            .small_button(text)
+                            .on_hover_text("recall value")
+                            .clicked()
+                        {
+                            event = value.magnitude().ok().map(Events::Value);
+                        }
+                        if ui.small_button("×").on_hover_text("delete").clicked() {
+                            event = Some(Events::DeleteVariable(name.clone()));
+                        }
+                    });
+                }
+                for function in self.calculator.functions() {
+                    ui.horizontal(|ui| {
+                        ui.label(function.to_string());
+                        if ui.small_button("×").on_hover_text("delete").clicked() {
+                            event = Some(Events::DeleteFunction(function.name.clone()));
+                        }
+                    });
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.variable_name).desired_width(40.0));
+                let valid = is_variable_name(&self.variable_name);
+                if ui
+                    .add_enabled(valid, egui::Button::new("Store"))
+                    .on_hover_text("store the shown number")
+                    .clicked()
+                {
+                    event = Some(Events::StoreVariable(std::mem::take(
+                        &mut self.variable_name,
+                    )));
+                }
+            });
+            ui.horizontal(|ui| {
+                let line =
+                    ui.add(egui::TextEdit::singleline(&mut self.definition).hint_text("f(x) ="));
+                let submitted = line.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+                if let Some(err) = &self.definition_error {
+                    line.on_hover_text(err.as_str());
+                }
+                if submitted {
+                    match self.calculator.evaluate(&self.definition) {
+                        Ok(result) => {
+                            self.definition.clear();
+                            self.definition_error = None;
+                            event = result.and_then(|q| q.magnitude().ok()).map(Events::Value);
+                        }
+                        Err(err) => self.definition_error = Some(err.to_string()),
+                    }
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                let path = ui.add(
+                    egui::TextEdit::singleline(&mut self.rates_path)
+                        .hint_text("rates.toml")
+                        .desired_width(100.0),
+                );
+                if let Some(err) = &self.rates_error {
+                    path.on_hover_text(err.as_str());
+                }
+                if ui
+                    .button("Load")
+                    .on_hover_text("load exchange rates")
+                    .clicked()
+                {
+                    match RateTable::load(self.rates_path.as_ref()) {
+                        Ok(rates) => {
+                            self.calculator.set_rates(Some(rates));
+                            self.rates_error = None;
+                        }
+                        Err(err) => self.rates_error = Some(err),
+                    }
+                }
+            });
+            match self.calculator.rates() {
+                Some(rates) => {
+                    let codes: Vec<_> = rates.currencies().collect();
+                    ui.label(format!("Rates of {} in {}", rates.date, rates.base))
+                        .on_hover_text(codes.join(" "));
+                }
+                None => {
+                    ui.weak("No exchange rates");
+                }
+            }
+            if let Some(event) = event {
+                self.calculator.dispatch(event);
+            }
+        });
+    }
+
+    /// Past calculations, newest on top. Clicking a result recalls the
+    /// value, clicking an expression recalls the whole calculation.
+    fn history_panel(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::right("history").show_animated(ctx, self.show_history, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("History");
+                if ui.small_button("Clear").clicked() {
+                    self.calculator.dispatch(Events::ClearHistory);
+                }
+            });
+            ui.separator();
+
+            let mut recall = None;
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (index, entry) in self.calculator.history().iter().enumerate().rev() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button(&entry.expression)
+                            .on_hover_text("recall expression")
+                            .clicked()
+                        {
+                            recall = Some(Events::RecallExpression(index));
+                        }
+                        ui.label("=");
+                        if ui
+                            .small_button(self.calculator.format(&entry.result))
+                            .on_hover_text("recall result")
+                            .clicked()
+                        {
+                            recall = Some(Events::RecallResult(index));
+                        }
+                    });
+                }
+            });
+            if let Some(event) = recall {
+                self.calculator.dispatch(event);
+            }
+        });
+    }
 }
 
 fn configure_text_styles(ctx: &egui::Context) {
@@ -37,77 +531,112 @@ fn configure_text_styles(ctx: &egui::Context) {
     ctx.set_style(style);
 }
 
-impl eframe::App for Calculator {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+impl eframe::App for CalculatorApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.handle_keyboard(ctx);
+        self.scientific_panel(ctx);
+        self.programmer_panel(ctx);
+        self.history_panel(ctx);
+        self.variables_panel(ctx);
+        let mode = self.mode;
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ctx.set_pixels_per_point(5.0);
             configure_text_styles(ctx);
 
+            // pending expression above the entry, both flush right
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                ui.label(
+                    egui::RichText::new(self.calculator.expression())
+                        .small()
+                        .weak(),
+                );
+            });
             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                let mut screen =
+                    ui.add_enabled(false, egui::Button::new(self.calculator.display()));
+                if let Some(err) = self.calculator.error() {
+                    screen = screen.on_disabled_hover_text(err.to_string());
+                }
+                if screen.clicked() {
+                    unreachable!();
+                }
+                ui.toggle_value(&mut self.show_history, "☰")
+                    .on_hover_text("history");
+                ui.toggle_value(&mut self.show_variables, "x=")
+                    .on_hover_text("variables");
                 if ui
-                    .add_enabled(false, egui::Button::new(self.display()))
+                    .small_button(self.mode.label())
+                    .on_hover_text("switch keypad")
                     .clicked()
                 {
-                    unreachable!();
+                    self.mode = self.mode.next();
+                }
+                if self.calculator.has_memory() {
+                    let contents: Vec<String> = (0..MEMORY_SLOTS)
+                        .map(|slot| {
+                            let value = self.calculator.format(self.calculator.memory(slot));
+                            format!("M{}: {}", slot + 1, value)
+                        })
+                        .collect();
+                    ui.label("M").on_hover_text(contents.join("\n"));
                 }
             });
 
             ui.horizontal(|ui| {
-                if ui.button("C").clicked() {
-                    self.dispatch(Events::Reset);
-                }
-                if ui.button("±").clicked() {
-                    self.dispatch(Events::Neg);
-                }
-                let _ = ui.button("(");
-                if ui.button("⌫").clicked() {
-                    self.dispatch(Events::Backspace);
+                let slot = self.memory_slot;
+                self.key(ui, "MC", Events::MemoryClear(slot));
+                self.key(ui, "MR", Events::MemoryRecall(slot));
+                self.key(ui, "M+", Events::MemoryAdd(slot));
+                self.key(ui, "M-", Events::MemorySub(slot));
+                self.key(ui, "MS", Events::MemoryStore(slot));
+            });
+            ui.horizontal(|ui| {
+                for slot in 0..MEMORY_SLOTS {
+                    ui.selectable_value(&mut self.memory_slot, slot, format!("M{}", slot + 1));
                 }
             });
+
+            ui.horizontal(|ui| {
+                self.key(ui, "C", Events::Reset);
+                self.key(ui, "±", Events::Neg);
+                self.key(ui, "(", Events::OpenParen);
+                self.key(ui, ")", Events::CloseParen);
+                self.key(ui, "x^y", Events::Pow);
+                self.key(ui, "⌫", Events::Backspace);
+            });
             ui.horizontal(|ui| {
                 for num in 1..4 {
-                    if ui.button(num.to_string()).clicked() {
-                        self.dispatch(Events::Number(num));
-                    }
-                }
-                if ui.button("+".to_string()).clicked() {
-                    self.dispatch(Events::Add);
+                    self.digit_key(ui, num);
                 }
+                self.key(ui, "+", Events::Add);
             });
             ui.horizontal(|ui| {
                 for num in 4..7 {
-                    if ui.button(num.to_string()).clicked() {
-                        self.dispatch(Events::Number(num));
-                    }
-                }
-                if ui.button("-".to_string()).clicked() {
-                    self.dispatch(Events::Sub);
+                    self.digit_key(ui, num);
                 }
+                self.key(ui, "-", Events::Sub);
             });
             ui.horizontal(|ui| {
                 for num in 7..10 {
-                    if ui.button(num.to_string()).clicked() {
-                        self.dispatch(Events::Number(num));
-                    }
-                }
-                if ui.button("*".to_string()).clicked() {
-                    self.dispatch(Events::Mul);
+                    self.digit_key(ui, num);
                 }
+                self.key(ui, "*", Events::Mul);
             });
             ui.horizontal(|ui| {
-                if ui.button("0".to_string()).clicked() {
-                    self.dispatch(Events::Number(0));
-                }
-                if ui.button(".".to_string()).clicked() {
-                    // float number ops
-                }
-                if ui.button("=".to_string()).clicked() {
-                    self.dispatch(Events::Eq);
-                }
-                if ui.button("/".to_string()).clicked() {
-                    self.dispatch(Events::Div);
-                }
+                self.digit_key(ui, 0);
+                let fractions = self.calculator.programmer().is_none();
+                ui.add_enabled_ui(fractions, |ui| self.key(ui, ".", Events::Decimal));
+                self.key(ui, "%", Events::Percent);
+                self.key(ui, "=", Events::Eq);
+                self.key(ui, "/", Events::Div);
             });
         });
+
+        if self.mode != mode {
+            frame.set_window_size(self.mode.window_size());
+            let programmer = (self.mode == Mode::Programmer).then_some(self.programmer);
+            self.calculator.set_programmer(programmer);
+        }
     }
 }